@@ -1,422 +1,771 @@
-use crate::{MILESTONE_LISTS, MILESTONE_SPACES};
+use crate::config::{Config, Space};
 
-use super::auth::ClickupToken;
+use super::client::ClickupClient;
+use super::error::ClickupError;
 use super::list::ListId;
 
-use super::task::{Task, TaskId};
-use serde::Serialize;
+use super::comment::NewComment;
+use super::custom_field::CustomField;
+use super::task::{NewTask, Task, TaskId, TaskUpdate};
+use super::team::{Team, TeamId};
+use super::user::User;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct MergeTasksParams<'a> {
+    source_task_ids: &'a [TaskId],
+}
 
-#[derive(Serialize, Clone, Hash)]
-struct CreateTaskParameters {
-    pub name: String,
-    pub description: String,
-    pub parent: String,
+#[derive(Deserialize)]
+struct CustomFieldsResponse {
+    pub fields: Vec<CustomField>,
 }
 
-#[derive(Serialize, Clone, Hash)]
-struct SetTaskParentParams<'a> {
-    pub parent: &'a TaskId,
+#[derive(Deserialize)]
+struct TeamsResponse {
+    pub teams: Vec<Team>,
 }
 
-/// Creates a clickup task
-pub async fn create_task(
-    token: &ClickupToken,
-    list: &ListId,
-    name: &str,
-) -> reqwest::Result<String> {
-    let client = reqwest::Client::new();
+#[derive(Deserialize)]
+struct UserResponse {
+    pub user: User,
+}
 
-    let url = format!("https://api.clickup.com/api/v2/list/{}/task", list.0);
+impl ClickupClient {
+    /// Creates a task in `list`, which becomes its home list.
+    pub async fn create_task(&self, list: &ListId, task: &NewTask) -> Result<Task, ClickupError> {
+        self.send_json(self.post(&format!("/list/{}/task", list.0)).json(task))
+            .await
+    }
 
-    let params = CreateTaskParameters {
-        name: String::from(name),
-        description: String::from("generated task"),
-        parent: String::from("36pnwzu"),
-    };
+    /// Gets a task, reusing it from the task cache when it was fetched recently.
+    pub async fn get_task(&self, id: &TaskId) -> Result<Task, ClickupError> {
+        let cache = self.connection().tasks();
+        if let Some(task) = cache.get(self.token(), id) {
+            return Ok(task);
+        }
 
-    client
-        .post(url)
-        .header(reqwest::header::AUTHORIZATION, token.0)
-        .json(&params)
-        .send()
-        .await?
-        .text()
-        .await
-}
+        let generation = cache.generation();
+        let task: Task = self.send_json(self.get(&format!("/task/{}", id.0))).await?;
+        cache.insert(self.token(), task.clone(), generation);
+        Ok(task)
+    }
 
-pub async fn get_task(token: &ClickupToken, id: &TaskId) -> reqwest::Result<Task> {
-    let client = reqwest::Client::new();
+    /// Applies `update` to the task, returning it as it is after the update.
+    pub async fn update_task(
+        &self,
+        id: &TaskId,
+        update: &TaskUpdate,
+    ) -> Result<Task, ClickupError> {
+        let result = self
+            .send_json::<Task>(self.put(&format!("/task/{}", id.0)).json(update))
+            .await;
+
+        // Other tokens see the change too, and fetches still in flight predate it.
+        let cache = self.connection().tasks();
+        cache.invalidate(id);
+        if let Ok(task) = &result {
+            cache.insert(self.token(), task.clone(), cache.generation());
+            for field in update.history_fields() {
+                self.connection().own_writes().record(id, field);
+            }
+        }
+        result
+    }
 
-    let url = format!("https://api.clickup.com/api/v2/task/{}", id.0);
+    pub async fn set_task_parent(
+        &self,
+        id: &TaskId,
+        new_parent: &TaskId,
+    ) -> Result<Task, ClickupError> {
+        self.update_task(id, &TaskUpdate::new().parent(new_parent.clone()))
+            .await
+    }
 
-    client
-        .get(url)
-        .header(reqwest::header::AUTHORIZATION, token.0)
-        .send()
-        .await?
-        .json()
-        .await
-}
+    pub async fn delete_task(&self, id: &TaskId) -> Result<(), ClickupError> {
+        let result = self.send(self.delete(&format!("/task/{}", id.0))).await;
+        self.connection().tasks().invalidate(id);
+        result
+    }
 
-pub async fn set_task_parent(
-    token: &ClickupToken,
-    id: &TaskId,
-    new_parent: &TaskId,
-) -> reqwest::Result<Task> {
-    let client = reqwest::Client::new();
+    /// Adds the task to `list` in addition to its home list.
+    pub async fn add_task_to_list(&self, task: &TaskId, list: &ListId) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.post(&format!("/list/{}/task/{}", list.0, task.0)))
+            .await;
+        self.connection().tasks().invalidate(task);
+        result
+    }
 
-    let url = format!("https://api.clickup.com/api/v2/task/{}", id.0);
+    /// Removes the task from a list it was added to, which cannot be its home list.
+    pub async fn remove_task_from_list(
+        &self,
+        task: &TaskId,
+        list: &ListId,
+    ) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.delete(&format!("/list/{}/task/{}", list.0, task.0)))
+            .await;
+        self.connection().tasks().invalidate(task);
+        result
+    }
 
-    let params = SetTaskParentParams { parent: new_parent };
+    /// Makes `list` the home list of the task, unlike [`Self::add_task_to_list`] which only
+    /// shows it in another list. Only version 3 of the API can do this.
+    pub async fn move_task_home_list(
+        &self,
+        team: TeamId,
+        task: &TaskId,
+        list: &ListId,
+    ) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.request_v3(
+                Method::PUT,
+                &format!("/workspaces/{team}/tasks/{}/home_list/{}", task.0, list.0),
+            ))
+            .await;
+        self.connection().tasks().invalidate(task);
+        if result.is_ok() {
+            self.connection().own_writes().record(task, "section_moved");
+        }
+        result
+    }
 
-    client
-        .put(url)
-        .header(reqwest::header::AUTHORIZATION, token.0)
-        .json(&params)
-        .send()
-        .await?
-        .json()
-        .await
-}
+    /// Merges the `sources` into `target`, which keeps their comments, attachments and
+    /// subtasks. The source tasks are deleted.
+    pub async fn merge_tasks(
+        &self,
+        target: &TaskId,
+        sources: &[TaskId],
+    ) -> Result<(), ClickupError> {
+        let result = self
+            .send(
+                self.post(&format!("/task/{}/merge", target.0))
+                    .json(&MergeTasksParams {
+                        source_task_ids: sources,
+                    }),
+            )
+            .await;
+
+        let cache = self.connection().tasks();
+        for id in sources.iter().chain([target]) {
+            cache.invalidate(id);
+        }
+        result
+    }
 
-pub async fn add_task_to_list(
-    token: &ClickupToken,
-    task: &TaskId,
-    list: &ListId,
-) -> reqwest::Result<String> {
-    let client = reqwest::Client::new();
+    /// Gets the teams the token has been authorized for.
+    pub async fn get_authorized_teams(&self) -> Result<Vec<Team>, ClickupError> {
+        let response: TeamsResponse = self.send_json(self.get("/team")).await?;
 
-    let url = format!(
-        "https://api.clickup.com/api/v2/list/{}/task/{}",
-        list.0, task.0
-    );
+        Ok(response.teams)
+    }
 
-    client
-        .post(url)
-        .header(reqwest::header::AUTHORIZATION, token.0)
-        .send()
-        .await?
-        .text()
-        .await
-}
+    /// Gets the user the token belongs to.
+    pub async fn get_authorized_user(&self) -> Result<User, ClickupError> {
+        let response: UserResponse = self.send_json(self.get("/user")).await?;
 
-fn task_is_in_milestone_space(task: &Task) -> bool {
-    MILESTONE_SPACES.contains(&task.space.id.as_str())
+        Ok(response.user)
+    }
+
+    /// Gets the custom fields that are available on tasks in `list`, including those defined on
+    /// its folder and space.
+    pub async fn get_accessible_custom_fields(
+        &self,
+        list: &ListId,
+    ) -> Result<Vec<CustomField>, ClickupError> {
+        let response: CustomFieldsResponse = self
+            .send_json(self.get(&format!("/list/{}/field", list.0)))
+            .await?;
+
+        Ok(response.fields)
+    }
 }
 
-fn task_is_in_milestone_list(task: &Task) -> bool {
-    let space_milestone_list = MILESTONE_SPACES
-        .iter()
-        .position(|&space| space == task.space.id.as_str())
-        .and_then(|space_pos| MILESTONE_LISTS.get(space_pos));
+fn task_is_in_milestone_space(config: &Config, task: &Task) -> bool {
+    config.space(&task.space.id).is_some()
+}
 
-    match space_milestone_list {
-        Some(list) => &task.list.id.0.as_str() == list,
+fn task_is_in_milestone_list(config: &Config, task: &Task) -> bool {
+    match config.space(&task.space.id) {
+        Some(space) => task.list.id == space.milestone_list,
         None => false,
     }
 }
 
-async fn task_is_transitive_subtask_of_milestone_task(
-    token: &ClickupToken,
+/// Whether the task is a milestone itself. Moved tasks end up in the milestone list as well, but
+/// always as subtasks of a milestone.
+fn task_is_milestone_task(config: &Config, task: &Task) -> bool {
+    task_is_in_milestone_list(config, task) && task.parent.is_none()
+}
+
+/// Walks up the parents of `task` to find the milestone task it is (transitively) a subtask of.
+async fn milestone_ancestor_of_task(
+    client: &ClickupClient,
+    config: &Config,
     task: &Task,
-) -> reqwest::Result<bool> {
-    if !task_is_in_milestone_space(task) {
-        return Ok(false);
+) -> Result<Option<Task>, ClickupError> {
+    if !task_is_in_milestone_space(config, task) {
+        return Ok(None);
     }
 
     let mut current_task = task.clone();
 
     while let Some(parent_id) = &current_task.parent {
-        let parent_task = get_task(token, parent_id).await?;
-        if task_is_in_milestone_list(&parent_task) {
-            return Ok(true);
+        let parent_task = client.get_task(parent_id).await?;
+        if task_is_in_milestone_list(config, &parent_task) {
+            return Ok(Some(parent_task));
         }
         current_task = parent_task;
     }
 
-    Ok(false)
+    Ok(None)
 }
 
-/// Gets the corresponding milestone destionation based on the custom `Milestone` field.
-/// TODO: Make easily configurable
-fn milestone_destionation_for_task(task: &Task) -> Option<TaskId> {
+pub async fn task_is_transitive_subtask_of_milestone_task(
+    client: &ClickupClient,
+    config: &Config,
+    task: &Task,
+) -> Result<bool, ClickupError> {
+    Ok(milestone_ancestor_of_task(client, config, task)
+        .await?
+        .is_some())
+}
+
+/// Gets the corresponding milestone destination based on the space's milestone field.
+fn milestone_destination_for_task(space: &Space, task: &Task) -> Option<TaskId> {
     task.custom_fields
         .iter()
-        .find(|cf| cf.name == "Milestone")
-        .and_then(|cf| cf.value.clone())
-        .and_then(|val| val.as_u64())
-        .map(|id| match id {
-            0 => TaskId::from("36w8251"), // None
-            1 => TaskId::from("36pnwzu"), // v0
-            2 => TaskId::from("36w74wp"), // v1
-            3 => TaskId::from("36w826q"), // v2
-            4 => TaskId::from("36w8281"), // v3
-            _ => TaskId::from("36w8251"), // None
-        })
+        .find(|cf| space.is_milestone_field(cf))
+        .and_then(CustomField::selected_option)
+        .and_then(|option| space.milestone_task(option))
+        .or(space.default_milestone.as_ref())
+        .cloned()
 }
 
+/// Moves a task under the milestone task selected by its milestone field, keeping it in the
+/// domain list it came from.
+///
+/// Tasks that are directly under a milestone task follow changes of their milestone field. Deeper
+/// subtasks are left alone, they belong to the milestone of their parent.
 pub async fn make_task_subtask_of_milestone_task_if_needed(
-    token: &ClickupToken,
+    client: &ClickupClient,
+    config: &Config,
     task: &Task,
-) -> reqwest::Result<()> {
-    if !task_is_in_milestone_space(task) {
+) -> Result<(), ClickupError> {
+    let Some(space) = config.space(&task.space.id) else {
+        return Ok(());
+    };
+    if task_is_milestone_task(config, task) {
         return Ok(());
     }
 
-    if task_is_transitive_subtask_of_milestone_task(token, task).await? {
-        return Ok(()); // already good, but note that we do not handle milestone changes correctly yet.
-    }
+    let current_milestone = match milestone_ancestor_of_task(client, config, task).await? {
+        Some(milestone) if task.parent.as_ref() == Some(&milestone.id) => Some(milestone.id),
+        Some(_) => return Ok(()),
+        None => None,
+    };
+
+    let Some(destination_task) = milestone_destination_for_task(space, task) else {
+        tracing::warn!(
+            "No milestone configured for task {:?} in space {}",
+            task.id,
+            space.id
+        );
+        return Ok(());
+    };
 
-    // The originial domain list, before it was moved to the milestone list
-    let domain_list_id = task.list.id.clone();
+    if current_milestone.as_ref() == Some(&destination_task) || destination_task == task.id {
+        return Ok(());
+    }
 
-    // TODO: Make easily configurable
+    // The domain lists the task lives in besides the milestone list. Before the first move that is
+    // the task's home list, after it the other lists the task was added to.
+    let domain_lists: Vec<ListId> = match current_milestone {
+        Some(_) => task
+            .locations
+            .iter()
+            .map(|list| list.id.clone())
+            .filter(|list| *list != space.milestone_list)
+            .collect(),
+        None => vec![task.list.id.clone()],
+    };
 
-    let destination_task = milestone_destionation_for_task(task)
-        .expect("ERROR: Invalid milestone task configuration in binary");
+    let moved_task = client.set_task_parent(&task.id, &destination_task).await?;
 
-    set_task_parent(token, &task.id, &destination_task).await?; //v0 milestone
+    for list in domain_lists {
+        let still_in_list =
+            moved_task.list.id == list || moved_task.locations.iter().any(|l| l.id == list);
+        if !still_in_list {
+            client.add_task_to_list(&task.id, &list).await?;
+        }
+    }
 
-    add_task_to_list(token, &task.id, &domain_list_id).await?;
+    if space.comment_on_move {
+        comment_on_move(client, space, &task.id, &destination_task).await;
+    }
 
     Ok(())
 }
 
-// pub async fn set_task_parent(authorization: &str
+/// Explains on a moved task why it was moved. The move already happened, so failing to comment
+/// is only logged.
+async fn comment_on_move(client: &ClickupClient, space: &Space, task: &TaskId, milestone: &TaskId) {
+    let name = match client.get_task(milestone).await {
+        Ok(Task {
+            name: Some(name), ..
+        }) => name,
+        _ => milestone.0.clone(),
+    };
+    let comment = NewComment::markdown(
+        &format!(
+            "Moved under milestone **{name}** because of its {} field.",
+            space.milestone_field
+        ),
+        &[],
+    );
+
+    if let Err(err) = client.create_task_comment(task, &comment).await {
+        tracing::warn!("Failed to comment on moved task {:?}: {}", task, err);
+    }
+}
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::CLICKUP_TOKEN;
+    use crate::clickup::{
+        auth::{ClickupToken, TokenStore},
+        client::{ClickupConnection, ClientConfig},
+        list::ListRef,
+        space::SpaceId,
+        space::SpaceRef,
+    };
+    use crate::config::test_config;
     use tracing_test::traced_test;
 
+    fn client() -> ClickupClient {
+        ClickupClient::new(
+            test_config().credentials[0]
+                .token
+                .clone()
+                .expect("the repository config uses a personal token"),
+        )
+    }
+
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn test_get_task() {
-        let res = get_task(&CLICKUP_TOKEN, &TaskId::from("36pnwzu"))
-            .await
-            .unwrap();
+        let res = client().get_task(&TaskId::from("36pnwzu")).await.unwrap();
         dbg!(res);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_is_in_milestone_space() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36pnwzu"))
-            .await
-            .unwrap();
+        let task = client().get_task(&TaskId::from("36pnwzu")).await.unwrap();
 
-        assert!(task_is_in_milestone_space(&task));
+        assert!(task_is_in_milestone_space(test_config(), &task));
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_is_not_in_milestone_space() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w78wt"))
-            .await
-            .unwrap();
+        let task = client().get_task(&TaskId::from("36w78wt")).await.unwrap();
 
-        assert!(!task_is_in_milestone_space(&task));
+        assert!(!task_is_in_milestone_space(test_config(), &task));
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn test_get_task_with_parent() {
-        let res = get_task(&CLICKUP_TOKEN, &TaskId::from("3vj469b"))
-            .await
-            .unwrap();
+        let res = client().get_task(&TaskId::from("3vj469b")).await.unwrap();
         dbg!(res);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn can_set_task_parent() {
-        let res = set_task_parent(
-            &CLICKUP_TOKEN,
-            &TaskId::from("36w79af"), // Task in picasso
-            &TaskId::from("36pnwzu"), // v0 milestone
-        )
-        .await
-        .unwrap();
+        let res = client()
+            .set_task_parent(
+                &TaskId::from("36w79af"), // Task in picasso
+                &TaskId::from("36pnwzu"), // v0 milestone
+            )
+            .await
+            .unwrap();
         dbg!(res);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn can_add_task_to_list() {
-        add_task_to_list(
-            &CLICKUP_TOKEN,
-            &TaskId::from("36w79af"), // Task that was originally in picasso
-            &ListId::from("188335750"), // picasso list
-        )
-        .await
-        .unwrap();
+        client()
+            .add_task_to_list(
+                &TaskId::from("36w79af"),   // Task that was originally in picasso
+                &ListId::from("188335750"), // picasso list
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_is_in_milestone_list() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w79af")) // Task that was originally in picasso
+        let task = client()
+            .get_task(&TaskId::from("36w79af")) // Task that was originally in picasso
             .await
             .unwrap();
 
-        assert!(task_is_in_milestone_list(&task));
+        assert!(task_is_in_milestone_list(test_config(), &task));
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_is_not_in_milestone_list() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7hq2")) // Test task that is not in milestone list
+        let task = client()
+            .get_task(&TaskId::from("36w7hq2")) // Test task that is not in milestone list
             .await
             .unwrap();
 
-        assert!(!task_is_in_milestone_list(&task));
+        assert!(!task_is_in_milestone_list(test_config(), &task));
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_in_different_space_is_not_in_milestone_list() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w78wt")) // Task that is in the unmanaged space
+        let task = client()
+            .get_task(&TaskId::from("36w78wt")) // Task that is in the unmanaged space
             .await
             .unwrap();
 
-        assert!(!task_is_in_milestone_list(&task));
+        assert!(!task_is_in_milestone_list(test_config(), &task));
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_is_transitive_subtask_of_milestone_task() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w79af")) // Task that is in the unmanaged space
+        let task = client()
+            .get_task(&TaskId::from("36w79af")) // Task that is in the unmanaged space
             .await
             .unwrap();
 
-        let is_subtask = task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &task)
-            .await
-            .unwrap();
+        let is_subtask =
+            task_is_transitive_subtask_of_milestone_task(&client(), test_config(), &task)
+                .await
+                .unwrap();
 
         assert!(is_subtask);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn subtask_that_is_transitive_subtask_of_milestone_task() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7rgq")) // Task that is in the unmanaged space
+        let task = client()
+            .get_task(&TaskId::from("36w7rgq")) // Task that is in the unmanaged space
             .await
             .unwrap();
 
-        let is_subtask = task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &task)
-            .await
-            .unwrap();
+        let is_subtask =
+            task_is_transitive_subtask_of_milestone_task(&client(), test_config(), &task)
+                .await
+                .unwrap();
 
         assert!(is_subtask);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn subsubtask_that_is_transitive_subtask_of_milestone_task() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7t30")) // Task that is in the unmanaged space
+        let task = client()
+            .get_task(&TaskId::from("36w7t30")) // Task that is in the unmanaged space
             .await
             .unwrap();
 
-        let is_subtask = task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &task)
-            .await
-            .unwrap();
+        let is_subtask =
+            task_is_transitive_subtask_of_milestone_task(&client(), test_config(), &task)
+                .await
+                .unwrap();
 
         assert!(is_subtask);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn subtask_that_is_not_transitive_subtask_of_milestone_task() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7q5g")) // Subtask that is not a subtask of a milestone task
+        let task = client()
+            .get_task(&TaskId::from("36w7q5g")) // Subtask that is not a subtask of a milestone task
             .await
             .unwrap();
 
-        let is_subtask = task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &task)
-            .await
-            .unwrap();
+        let is_subtask =
+            task_is_transitive_subtask_of_milestone_task(&client(), test_config(), &task)
+                .await
+                .unwrap();
 
         assert!(!is_subtask);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn subsubtask_that_is_not_transitive_subtask_of_milestone_task() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7qpy")) // subsubtask that is not a subtask of a milestone task
+        let task = client()
+            .get_task(&TaskId::from("36w7qpy")) // subsubtask that is not a subtask of a milestone task
             .await
             .unwrap();
 
-        let is_subtask = task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &task)
-            .await
-            .unwrap();
+        let is_subtask =
+            task_is_transitive_subtask_of_milestone_task(&client(), test_config(), &task)
+                .await
+                .unwrap();
 
         assert!(!is_subtask);
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_should_move_if_needed() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7wbr")) // task that should get moved
+        let task = client()
+            .get_task(&TaskId::from("36w7wbr")) // task that should get moved
             .await
             .unwrap();
 
-        make_task_subtask_of_milestone_task_if_needed(&CLICKUP_TOKEN, &task)
+        make_task_subtask_of_milestone_task_if_needed(&client(), test_config(), &task)
             .await
             .unwrap();
 
-        let moved_task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w7wbr"))
-            .await
-            .unwrap();
+        let moved_task = client().get_task(&TaskId::from("36w7wbr")).await.unwrap();
 
-        assert!(
-            task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &moved_task)
-                .await
-                .unwrap()
-        );
+        assert!(task_is_transitive_subtask_of_milestone_task(
+            &client(),
+            test_config(),
+            &moved_task
+        )
+        .await
+        .unwrap());
     }
 
     #[tokio::test]
     #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
     async fn task_that_should_move_to_v2() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w83z6")) // task that should move to v2
+        let task = client()
+            .get_task(&TaskId::from("36w83z6")) // task that should move to v2
             .await
             .unwrap();
 
-        make_task_subtask_of_milestone_task_if_needed(&CLICKUP_TOKEN, &task)
+        make_task_subtask_of_milestone_task_if_needed(&client(), test_config(), &task)
+            .await
+            .unwrap();
+
+        let moved_task = client().get_task(&TaskId::from("36w83z6")).await.unwrap();
+
+        assert!(task_is_transitive_subtask_of_milestone_task(
+            &client(),
+            test_config(),
+            &moved_task
+        )
+        .await
+        .unwrap());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
+    async fn task_that_should_move_to_v3() {
+        let task = client()
+            .get_task(&TaskId::from("36w861w")) // task that should move to v2
             .await
             .unwrap();
 
-        let moved_task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w83z6"))
+        make_task_subtask_of_milestone_task_if_needed(&client(), test_config(), &task)
             .await
             .unwrap();
 
-        assert!(
-            task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &moved_task)
+        let moved_task = client().get_task(&TaskId::from("36w861w")).await.unwrap();
+
+        assert!(task_is_transitive_subtask_of_milestone_task(
+            &client(),
+            test_config(),
+            &moved_task
+        )
+        .await
+        .unwrap());
+    }
+
+    fn milestone_config() -> Config {
+        r#"
+            [[credentials]]
+            name = "clickup"
+            token = "pk_test"
+
+            [[spaces]]
+            id = "32279886"
+            milestone_list = "188335476"
+            credentials = "clickup"
+            default_milestone = "36w8251"
+
+            [[spaces.milestones]]
+            option = "v0"
+            task = "36pnwzu"
+        "#
+        .parse()
+        .unwrap()
+    }
+
+    fn milestone_space() -> Space {
+        milestone_config().spaces[0].clone()
+    }
+
+    fn task_in(space: &str, list: &str, parent: Option<&str>) -> Task {
+        Task {
+            id: TaskId::from("36w79af"),
+            parent: parent.map(TaskId::from),
+            list: ListRef {
+                id: ListId::from(list),
+                name: None,
+            },
+            space: SpaceRef {
+                id: SpaceId::from(space),
+                name: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_tasks_in_milestone_space_and_list() {
+        let config = &milestone_config();
+
+        let subtask = task_in("32279886", "188335476", Some("36pnwzu"));
+        assert!(task_is_in_milestone_space(config, &subtask));
+        assert!(task_is_in_milestone_list(config, &subtask));
+
+        let domain_task = task_in("32279886", "188335750", None);
+        assert!(task_is_in_milestone_space(config, &domain_task));
+        assert!(!task_is_in_milestone_list(config, &domain_task));
+
+        let unmanaged = task_in("32279887", "188335476", None);
+        assert!(!task_is_in_milestone_space(config, &unmanaged));
+        assert!(!task_is_in_milestone_list(config, &unmanaged));
+    }
+
+    fn task_with_milestone(value: serde_json::Value) -> Task {
+        serde_json::from_value(serde_json::json!({
+            "id": "36w7wbr",
+            "custom_fields": [{
+                "id": "5dc86497-098d-4bb0-87d6-cf28e43812e7",
+                "name": "Milestone",
+                "type": "drop_down",
+                "type_config": {
+                    "options": [
+                        { "id": "a1", "name": "None", "color": null, "orderindex": 0 },
+                        { "id": "b2", "name": "v0", "color": "#04A9F4", "orderindex": 1 },
+                        { "id": "c3", "name": "v4", "color": null, "orderindex": 5 }
+                    ]
+                },
+                "value": value
+            }],
+            "list": { "id": "188335750" },
+            "folder": { "id": "115982530" },
+            "space": { "id": "32279886" }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn leaves_milestone_tasks_alone() {
+        // Any request fails, so a milestone task is only left alone if none is made.
+        let connection = ClickupConnection::new(&ClientConfig {
+            base_url: "http://127.0.0.1:9/api/v2".into(),
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        let client = connection.client(ClickupToken("pk_test".into()));
+        let config = &milestone_config();
+
+        for milestone in ["36pnwzu", "36w8251"] {
+            let task = Task {
+                id: TaskId::from(milestone),
+                ..task_in("32279886", "188335476", None)
+            };
+            make_task_subtask_of_milestone_task_if_needed(&client, config, &task)
                 .await
-                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn milestone_destination_follows_selected_option() {
+        let space = &milestone_space();
+
+        assert_eq!(
+            milestone_destination_for_task(space, &task_with_milestone(1.into())),
+            Some(TaskId::from("36pnwzu"))
+        );
+    }
+
+    #[test]
+    fn milestone_destination_falls_back_to_default() {
+        let space = &milestone_space();
+
+        assert_eq!(
+            milestone_destination_for_task(space, &task_with_milestone(5.into())),
+            Some(TaskId::from("36w8251"))
+        );
+        assert_eq!(
+            milestone_destination_for_task(space, &task_with_milestone(serde_json::Value::Null)),
+            Some(TaskId::from("36w8251"))
         );
     }
 
     #[tokio::test]
     #[traced_test]
-    async fn task_that_should_move_to_v3() {
-        let task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w861w")) // task that should move to v2
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
+    async fn milestone_configuration_matches_clickup() {
+        let tokens =
+            TokenStore::open(std::env::temp_dir().join("clicky-test-tokens.json")).unwrap();
+
+        test_config()
+            .verify(&ClickupConnection::default(), &tokens)
             .await
             .unwrap();
+    }
 
-        make_task_subtask_of_milestone_task_if_needed(&CLICKUP_TOKEN, &task)
+    #[tokio::test]
+    #[traced_test]
+    #[ignore = "talks to ClickUp, needs CLICKUP_TOKEN"]
+    async fn task_follows_milestone_change() {
+        let task_id = TaskId::from("36w83z6"); // task that should move to v2
+
+        client()
+            .set_task_parent(&task_id, &TaskId::from("36pnwzu")) // v0 milestone
             .await
             .unwrap();
+        let task = client().get_task(&task_id).await.unwrap();
 
-        let moved_task = get_task(&CLICKUP_TOKEN, &TaskId::from("36w861w"))
+        make_task_subtask_of_milestone_task_if_needed(&client(), test_config(), &task)
             .await
             .unwrap();
 
-        assert!(
-            task_is_transitive_subtask_of_milestone_task(&CLICKUP_TOKEN, &moved_task)
-                .await
-                .unwrap()
-        );
+        let moved_task = client().get_task(&task_id).await.unwrap();
+        assert_eq!(moved_task.parent, Some(TaskId::from("36w826q"))); // v2 milestone
+        assert_eq!(moved_task.locations, task.locations);
     }
 }
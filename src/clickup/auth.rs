@@ -1 +1,215 @@
-pub struct ClickupToken(pub(crate) &'static str);
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::{client::ClickupConnection, error::ClickupError, team::TeamId};
+use crate::{secret::Secret, store::JsonStore};
+
+/// A ClickUp API token, sent as the `Authorization` header.
+///
+/// Either a personal token or an access token obtained through the [`OAuthApp`] flow.
+#[derive(Clone, serde::Deserialize)]
+#[serde(from = "Secret")]
+pub struct ClickupToken(pub(crate) String);
+
+impl From<Secret> for ClickupToken {
+    fn from(secret: Secret) -> Self {
+        Self(secret.expose().to_owned())
+    }
+}
+
+impl ClickupToken {
+    /// Tells the token apart from others, for keeping state per token without holding on to it.
+    pub fn key(&self) -> TokenKey {
+        TokenKey(Sha256::digest(self.0.as_bytes()).into())
+    }
+}
+
+impl fmt::Debug for ClickupToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClickupToken(<redacted>)")
+    }
+}
+
+/// The SHA-256 hash of a [`ClickupToken`], which can be logged and kept in maps in its place.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenKey([u8; 32]);
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TokenKey({})", hex::encode(&self.0[..4]))
+    }
+}
+
+/// How long a user has to complete the authorization page before its state expires.
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// A ClickUp OAuth app, created under the workspace's settings > integrations.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OAuthApp {
+    pub client_id: String,
+    pub client_secret: Secret,
+    /// The redirect url registered for the app, pointing at clicky's `/oauth/callback`.
+    pub redirect_uri: String,
+}
+
+#[derive(Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+}
+
+impl OAuthApp {
+    /// The ClickUp page where a user picks the workspaces to install the app in.
+    pub fn authorize_url(&self, state: &str) -> reqwest::Url {
+        reqwest::Url::parse_with_params(
+            "https://app.clickup.com/api",
+            &[
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("state", state),
+            ],
+        )
+        .expect("authorize url is valid")
+    }
+
+    /// Exchanges the code ClickUp passed to the callback for an access token.
+    pub async fn exchange_code(
+        &self,
+        connection: &ClickupConnection,
+        code: &str,
+    ) -> Result<ClickupToken, ClickupError> {
+        let response: AccessTokenResponse = connection
+            .send_json(
+                None,
+                connection
+                    .request(reqwest::Method::POST, "/oauth/token")
+                    .query(&[
+                        ("client_id", self.client_id.as_str()),
+                        ("client_secret", self.client_secret.expose()),
+                        ("code", code),
+                    ]),
+            )
+            .await?;
+
+        Ok(ClickupToken(response.access_token))
+    }
+}
+
+/// The authorization-code flow of an [`OAuthApp`], tracking the authorizations in progress.
+#[derive(Debug)]
+pub struct OAuthFlow {
+    pub app: OAuthApp,
+    pending: Mutex<HashMap<String, Instant>>,
+}
+
+impl OAuthFlow {
+    pub fn new(app: OAuthApp) -> Self {
+        Self {
+            app,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts an authorization, returning the url to send the user to.
+    pub fn start(&self) -> reqwest::Url {
+        let state = uuid::Uuid::new_v4().to_string();
+
+        let mut pending = self.pending.lock().expect("oauth lock poisoned");
+        pending.retain(|_, started| started.elapsed() < AUTHORIZATION_TIMEOUT);
+        pending.insert(state.clone(), Instant::now());
+
+        self.app.authorize_url(&state)
+    }
+
+    /// Checks that a callback belongs to an authorization started by [`OAuthFlow::start`].
+    ///
+    /// A state is only accepted once.
+    pub fn finish(&self, state: &str) -> bool {
+        let mut pending = self.pending.lock().expect("oauth lock poisoned");
+        matches!(pending.remove(state), Some(started) if started.elapsed() < AUTHORIZATION_TIMEOUT)
+    }
+}
+
+/// OAuth access tokens of the workspaces clicky is installed in.
+#[derive(Debug)]
+pub struct TokenStore(JsonStore<TeamId, Secret>);
+
+impl TokenStore {
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        JsonStore::open(path).map(Self)
+    }
+
+    pub fn get(&self, team: TeamId) -> Option<ClickupToken> {
+        self.0.get(&team).map(ClickupToken::from)
+    }
+
+    pub fn insert(&self, team: TeamId, token: &ClickupToken) -> io::Result<()> {
+        self.0.insert(team, Secret::new(token.0.clone()))
+    }
+
+    pub fn teams(&self) -> Vec<TeamId> {
+        self.0.entries().into_iter().map(|(team, _)| team).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow() -> OAuthFlow {
+        OAuthFlow::new(OAuthApp {
+            client_id: "client".into(),
+            client_secret: Secret::new("secret"),
+            redirect_uri: "https://clickity.fly.dev/oauth/callback".into(),
+        })
+    }
+
+    #[test]
+    fn authorize_url_carries_state() {
+        let flow = flow();
+        let url = flow.start();
+        let state = url
+            .query_pairs()
+            .find(|(key, _)| key == "state")
+            .map(|(_, state)| state.into_owned())
+            .unwrap();
+
+        assert!(url.as_str().starts_with(
+            "https://app.clickup.com/api?client_id=client&redirect_uri=https%3A%2F%2Fclickity.fly.dev%2Foauth%2Fcallback"
+        ));
+        assert!(flow.finish(&state));
+        assert!(!flow.finish(&state), "a state is only accepted once");
+    }
+
+    #[test]
+    fn rejects_unknown_state() {
+        assert!(!flow().finish("forged"));
+    }
+
+    #[test]
+    fn stores_tokens_without_showing_them() {
+        let path = std::env::temp_dir()
+            .join(format!("clicky-tokens-{}", uuid::Uuid::new_v4()))
+            .join("oauth_tokens.json");
+        let team = TeamId::from(20131398);
+
+        let tokens = TokenStore::open(&path).unwrap();
+        tokens
+            .insert(team, &ClickupToken("4401289_SECRET".into()))
+            .unwrap();
+        assert!(!format!("{tokens:?}").contains("SECRET"));
+
+        let token = TokenStore::open(&path).unwrap().get(team).unwrap();
+        assert_eq!(token.0, "4401289_SECRET");
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use super::{
+    auth::{ClickupToken, TokenKey},
+    task::{Task, TaskId},
+};
+
+/// Expired entries are dropped once the cache grows beyond this many tasks.
+const PURGE_THRESHOLD: usize = 10_000;
+
+/// Tasks fetched from ClickUp, kept for a while so walking up the same parents again does not
+/// need more requests.
+///
+/// Tasks are kept per token, as ClickUp decides per user which tasks they may see. Entries expire
+/// after the TTL, and the webhook endpoint invalidates the tasks that events report as changed.
+#[derive(Debug)]
+pub struct TaskCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    tasks: HashMap<TaskId, Entry>,
+    /// Counts invalidations, so fetches started before one can be told apart.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// The generation the task was last invalidated in, and when.
+    invalidated: Option<(u64, Instant)>,
+    /// The task as fetched with each token.
+    fetched: HashMap<TokenKey, (Instant, Task)>,
+}
+
+/// When a fetch started, in terms of invalidations. See [`TaskCache::generation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CacheGeneration(u64);
+
+/// How well the [`TaskCache`] is doing, for the admin endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl TaskCache {
+    /// A cache keeping tasks for `ttl`, a zero TTL disables it.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The task as last fetched with `token`.
+    pub fn get(&self, token: &ClickupToken, id: &TaskId) -> Option<Task> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let task =
+            entries
+                .tasks
+                .get_mut(id)
+                .and_then(|entry| match entry.fetched.get(&token.key()) {
+                    Some((fetched, task)) if fetched.elapsed() < self.ttl => Some(task.clone()),
+                    Some(_) => {
+                        entry.fetched.remove(&token.key());
+                        None
+                    }
+                    None => None,
+                });
+
+        let counter = if task.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        task
+    }
+
+    /// Taken before fetching tasks, and passed to [`Self::insert`] with the fetched tasks.
+    pub fn generation(&self) -> CacheGeneration {
+        CacheGeneration(self.entries.lock().unwrap().generation)
+    }
+
+    /// Keeps a task fetched with `token`, unless it was invalidated after the fetch started at
+    /// `since`, in which case it may predate the change.
+    pub fn insert(&self, token: &ClickupToken, task: Task, since: CacheGeneration) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.tasks.len() >= PURGE_THRESHOLD {
+            self.purge(&mut entries);
+        }
+
+        let entry = entries
+            .tasks
+            .entry(task.id.clone())
+            .or_insert_with(|| Entry {
+                invalidated: None,
+                fetched: HashMap::new(),
+            });
+        if entry
+            .invalidated
+            .is_some_and(|(generation, _)| generation > since.0)
+        {
+            return;
+        }
+        entry.fetched.insert(token.key(), (Instant::now(), task));
+    }
+
+    /// Drops the task for every token, and rejects the results of fetches still in flight.
+    pub fn invalidate(&self, id: &TaskId) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        let invalidated = Some((entries.generation, Instant::now()));
+
+        let entry = entries.tasks.entry(id.clone()).or_insert_with(|| Entry {
+            invalidated,
+            fetched: HashMap::new(),
+        });
+        entry.invalidated = invalidated;
+        entry.fetched.clear();
+    }
+
+    /// Drops expired tasks, and invalidations older than the TTL, as no fetch takes that long.
+    fn purge(&self, entries: &mut Entries) {
+        entries.tasks.retain(|_, entry| {
+            entry
+                .fetched
+                .retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+            let recently_invalidated = entry
+                .invalidated
+                .is_some_and(|(_, at)| at.elapsed() < self.ttl);
+            !entry.fetched.is_empty() || recently_invalidated
+        });
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            entries: entries
+                .tasks
+                .values()
+                .map(|entry| entry.fetched.len())
+                .sum(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str) -> Task {
+        Task {
+            id: TaskId::from(id),
+            ..Default::default()
+        }
+    }
+
+    fn token() -> ClickupToken {
+        ClickupToken("pk_test".into())
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let cache = TaskCache::new(Duration::from_secs(60));
+        let id = TaskId::from("36w7wbr");
+
+        assert_eq!(cache.get(&token(), &id), None);
+        cache.insert(&token(), task("36w7wbr"), cache.generation());
+        assert_eq!(cache.get(&token(), &id), Some(task("36w7wbr")));
+
+        cache.invalidate(&id);
+        assert_eq!(cache.get(&token(), &id), None);
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                entries: 0,
+                hits: 1,
+                misses: 2
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_tasks_per_token() {
+        let cache = TaskCache::new(Duration::from_secs(60));
+        let id = TaskId::from("36w7wbr");
+        let other = ClickupToken("pk_other".into());
+
+        cache.insert(&token(), task("36w7wbr"), cache.generation());
+
+        assert_eq!(cache.get(&other, &id), None);
+        assert_eq!(cache.get(&token(), &id), Some(task("36w7wbr")));
+    }
+
+    #[test]
+    fn keeps_tasks_without_the_token() {
+        let cache = TaskCache::new(Duration::from_secs(60));
+        cache.insert(
+            &ClickupToken("pk_4401289_SECRET".into()),
+            task("36w7wbr"),
+            cache.generation(),
+        );
+
+        assert!(!format!("{cache:?}").contains("SECRET"));
+    }
+
+    #[test]
+    fn rejects_fetches_started_before_invalidation() {
+        let cache = TaskCache::new(Duration::from_secs(60));
+        let id = TaskId::from("36w7wbr");
+
+        let before = cache.generation();
+        cache.invalidate(&id);
+        cache.insert(&token(), task("36w7wbr"), before);
+        assert_eq!(cache.get(&token(), &id), None);
+
+        cache.insert(&token(), task("36w7wbr"), cache.generation());
+        assert_eq!(cache.get(&token(), &id), Some(task("36w7wbr")));
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = TaskCache::new(Duration::from_millis(10));
+        cache.insert(&token(), task("36w7wbr"), cache.generation());
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(cache.get(&token(), &TaskId::from("36w7wbr")), None);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn zero_ttl_disables_cache() {
+        let cache = TaskCache::new(Duration::ZERO);
+        cache.insert(&token(), task("36w7wbr"), cache.generation());
+
+        assert_eq!(cache.get(&token(), &TaskId::from("36w7wbr")), None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    task::TaskId,
+    timestamp,
+    user::{User, UserId},
+};
+
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct ChecklistId(pub(crate) String);
+
+impl From<&str> for ChecklistId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for ChecklistId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct ChecklistItemId(pub(crate) String);
+
+impl From<&str> for ChecklistItemId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for ChecklistItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checklist {
+    pub id: ChecklistId,
+    pub name: String,
+    pub task_id: Option<TaskId>,
+    pub orderindex: Option<f64>,
+    #[serde(default, with = "timestamp::option")]
+    pub date_created: Option<DateTime<Utc>>,
+    pub creator: Option<UserId>,
+    /// Number of resolved items.
+    #[serde(default)]
+    pub resolved: u32,
+    #[serde(default)]
+    pub unresolved: u32,
+    #[serde(default)]
+    pub items: Vec<ChecklistItem>,
+}
+
+impl Checklist {
+    /// Whether every item of the checklist is resolved, which an empty checklist is.
+    pub fn is_resolved(&self) -> bool {
+        self.unresolved == 0
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    pub id: ChecklistItemId,
+    pub name: String,
+    pub orderindex: Option<f64>,
+    pub assignee: Option<User>,
+    #[serde(default)]
+    pub resolved: bool,
+    /// The item this one is nested under.
+    pub parent: Option<ChecklistItemId>,
+    #[serde(default, with = "timestamp::option")]
+    pub date_created: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub children: Vec<ChecklistItem>,
+}
+
+/// Changes to a checklist item, `None` leaves a part of it as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct ChecklistItemUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Assigns the item to a user, or unassigns it with `Some(None)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<Option<UserId>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<bool>,
+    /// Nests the item under another one, or moves it to the top level with `Some(None)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Option<ChecklistItemId>>,
+}
+
+#[derive(Serialize)]
+struct ChecklistParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct NewItemParams<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<UserId>,
+}
+
+#[derive(Deserialize)]
+struct ChecklistResponse {
+    checklist: Checklist,
+}
+
+impl ClickupClient {
+    pub async fn create_checklist(
+        &self,
+        task: &TaskId,
+        name: &str,
+    ) -> Result<Checklist, ClickupError> {
+        let params = ChecklistParams {
+            name: Some(name),
+            position: None,
+        };
+
+        let response: ChecklistResponse = self
+            .send_json(
+                self.post(&format!("/task/{}/checklist", task.0))
+                    .json(&params),
+            )
+            .await?;
+        self.connection().tasks().invalidate(task);
+        Ok(response.checklist)
+    }
+
+    /// Renames the checklist and moves it to `position` among the task's checklists, 0 being
+    /// the first.
+    pub async fn edit_checklist(
+        &self,
+        id: &ChecklistId,
+        name: Option<&str>,
+        position: Option<u32>,
+    ) -> Result<(), ClickupError> {
+        self.send(
+            self.put(&format!("/checklist/{id}"))
+                .json(&ChecklistParams { name, position }),
+        )
+        .await
+    }
+
+    pub async fn delete_checklist(&self, id: &ChecklistId) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/checklist/{id}"))).await
+    }
+
+    /// Adds an item to the checklist, returning the checklist with it.
+    pub async fn create_checklist_item(
+        &self,
+        checklist: &ChecklistId,
+        name: &str,
+        assignee: Option<UserId>,
+    ) -> Result<Checklist, ClickupError> {
+        let response: ChecklistResponse = self
+            .send_json(
+                self.post(&format!("/checklist/{checklist}/checklist_item"))
+                    .json(&NewItemParams { name, assignee }),
+            )
+            .await?;
+        self.invalidate_checklist_task(&response.checklist);
+        Ok(response.checklist)
+    }
+
+    /// Applies `update` to the item, returning its checklist as it is after the update.
+    pub async fn edit_checklist_item(
+        &self,
+        checklist: &ChecklistId,
+        item: &ChecklistItemId,
+        update: &ChecklistItemUpdate,
+    ) -> Result<Checklist, ClickupError> {
+        let response: ChecklistResponse = self
+            .send_json(
+                self.put(&format!("/checklist/{checklist}/checklist_item/{item}"))
+                    .json(update),
+            )
+            .await?;
+        self.invalidate_checklist_task(&response.checklist);
+        Ok(response.checklist)
+    }
+
+    pub async fn delete_checklist_item(
+        &self,
+        checklist: &ChecklistId,
+        item: &ChecklistItemId,
+    ) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/checklist/{checklist}/checklist_item/{item}")))
+            .await
+    }
+
+    fn invalidate_checklist_task(&self, checklist: &Checklist) {
+        if let Some(task) = &checklist.task_id {
+            self.connection().tasks().invalidate(task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn item_update_clears_with_null() {
+        let update = ChecklistItemUpdate {
+            resolved: Some(true),
+            assignee: Some(None),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({ "assignee": null, "resolved": true })
+        );
+    }
+}
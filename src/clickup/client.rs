@@ -0,0 +1,460 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::Rng;
+use reqwest::{header::HeaderMap, Method, RequestBuilder};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::{
+    auth::{ClickupToken, TokenKey},
+    cache::TaskCache,
+    error::ClickupError,
+    writes::OwnWrites,
+};
+
+/// The ClickUp API all requests go to unless the config points elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.clickup.com/api/v2";
+
+/// Characters left as they are in path segments, as in `encodeURIComponent`.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// Encodes a user provided name, such as a tag, to be used as one segment of a request path.
+pub(crate) fn path_segment(name: &str) -> String {
+    utf8_percent_encode(name, PATH_SEGMENT).to_string()
+}
+
+/// How clicky connects to the ClickUp API, the `[clickup]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ClientConfig {
+    /// Root of the API, such as a local mock in development.
+    pub base_url: String,
+    /// Limit on a whole request, from connecting to reading the response.
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub user_agent: String,
+    /// Retries of a request that was rate limited or failed on ClickUp's side.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry and jittered.
+    pub retry_delay_ms: u64,
+    /// Requests are held back until the rate limit resets once a token has this few left.
+    pub min_remaining: u32,
+    /// How long fetched tasks are reused, 0 disables the task cache.
+    pub task_cache_ttl_secs: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_owned(),
+            max_retries: 3,
+            retry_delay_ms: 500,
+            min_remaining: 5,
+            task_cache_ttl_secs: 60,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// The jittered delay before retry number `retry`, counting from 0.
+    fn retry_delay(&self, retry: u32) -> Duration {
+        let delay = Duration::from_millis(self.retry_delay_ms) * 2u32.saturating_pow(retry);
+        delay / 2 + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+    }
+}
+
+/// The rate limit of a token as reported by ClickUp with every response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Requests left until `reset`.
+    pub remaining: u32,
+    pub reset: SystemTime,
+}
+
+impl RateLimit {
+    pub const REMAINING_HEADER: &'static str = "X-RateLimit-Remaining";
+    /// Unix timestamp in seconds at which the limit resets.
+    pub const RESET_HEADER: &'static str = "X-RateLimit-Reset";
+
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name)?.to_str().ok()?.trim().parse::<u64>().ok();
+
+        Some(Self {
+            remaining: header(Self::REMAINING_HEADER)?
+                .try_into()
+                .unwrap_or(u32::MAX),
+            reset: UNIX_EPOCH + Duration::from_secs(header(Self::RESET_HEADER)?),
+        })
+    }
+
+    /// How long until the limit resets, zero when it already has.
+    pub fn until_reset(&self) -> Duration {
+        self.reset
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    }
+}
+
+/// A pool of HTTP connections to the ClickUp API, shared by the clients of all credentials.
+///
+/// Cloning it is cheap and keeps using the same pool. It tracks the rate limit of every token it
+/// sends requests for, holding requests back before the limit is hit and retrying rate limited
+/// and failed requests with backoff.
+#[derive(Debug, Clone)]
+pub struct ClickupConnection {
+    http: reqwest::Client,
+    base_url: Arc<str>,
+    config: Arc<ClientConfig>,
+    rate_limits: Arc<Mutex<HashMap<TokenKey, RateLimit>>>,
+    tasks: Arc<TaskCache>,
+    writes: Arc<OwnWrites>,
+}
+
+impl ClickupConnection {
+    pub fn new(config: &ClientConfig) -> reqwest::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .user_agent(&config.user_agent)
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: config.base_url.trim_end_matches('/').into(),
+            config: Arc::new(config.clone()),
+            rate_limits: Arc::default(),
+            tasks: Arc::new(TaskCache::new(Duration::from_secs(
+                config.task_cache_ttl_secs,
+            ))),
+            writes: Arc::default(),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Tasks recently fetched over this connection, by token.
+    pub fn tasks(&self) -> &TaskCache {
+        &self.tasks
+    }
+
+    /// Changes recently made to tasks over this connection.
+    pub fn own_writes(&self) -> &OwnWrites {
+        &self.writes
+    }
+
+    /// A client making requests with `token` over this connection.
+    pub fn client(&self, token: ClickupToken) -> ClickupClient {
+        ClickupClient {
+            connection: self.clone(),
+            token,
+        }
+    }
+
+    /// Starts a request to `path` of the API, without authorization.
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+    }
+
+    /// Starts a request to `path` of version 3 of the API, which some operations are only
+    /// available in. It is found next to the configured version 2 base URL.
+    pub(crate) fn request_v3(&self, method: Method, path: &str) -> RequestBuilder {
+        let root = self.base_url.strip_suffix("/v2").unwrap_or(&self.base_url);
+        self.http.request(method, format!("{root}/v3{path}"))
+    }
+
+    /// The last rate limit ClickUp reported for `token`.
+    pub fn rate_limit(&self, token: &ClickupToken) -> Option<RateLimit> {
+        self.rate_limits.lock().unwrap().get(&token.key()).copied()
+    }
+
+    /// Waits for the rate limit of `token` to reset when it has almost no requests left.
+    async fn throttle(&self, token: Option<&ClickupToken>) {
+        let Some(limit) = token.and_then(|token| self.rate_limit(token)) else {
+            return;
+        };
+
+        if limit.remaining <= self.config.min_remaining {
+            let wait = limit.until_reset();
+            if !wait.is_zero() {
+                tracing::warn!(
+                    "{} ClickUp requests left, waiting {:?} for the rate limit to reset",
+                    limit.remaining,
+                    wait
+                );
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+
+    /// Sends a request for `token`, turning error statuses into a [`ClickupError`] with
+    /// ClickUp's message.
+    ///
+    /// Rate limited requests are retried with jittered exponential backoff, no earlier than the
+    /// reset. Requests that failed with a server error or could not be sent are retried the same
+    /// way unless they are `POST`s, see [`ClickupError::is_retryable_request`]. The request must
+    /// have been started with [`Self::request`] or [`Self::request_v3`].
+    pub(crate) async fn send(
+        &self,
+        token: Option<&ClickupToken>,
+        request: RequestBuilder,
+    ) -> Result<bytes::Bytes, ClickupError> {
+        let mut request = request.build()?;
+        let method = request.method().clone();
+
+        let mut retry = 0;
+        loop {
+            let next = request.try_clone();
+
+            self.throttle(token).await;
+            let (err, limit) = match self.send_once(token, request).await {
+                Ok(body) => return Ok(body),
+                Err(failure) => failure,
+            };
+
+            let next = match next {
+                Some(next)
+                    if retry < self.config.max_retries && err.is_retryable_request(&method) =>
+                {
+                    next
+                }
+                _ => return Err(err),
+            };
+
+            let mut delay = self.config.retry_delay(retry);
+            if let (ClickupError::RateLimited(_), Some(limit)) = (&err, limit) {
+                delay = delay.max(limit.until_reset());
+            }
+            tracing::warn!("Retrying ClickUp request in {:?}: {}", delay, err);
+            tokio::time::sleep(delay).await;
+
+            request = next;
+            retry += 1;
+        }
+    }
+
+    async fn send_once(
+        &self,
+        token: Option<&ClickupToken>,
+        request: reqwest::Request,
+    ) -> Result<bytes::Bytes, (ClickupError, Option<RateLimit>)> {
+        let response = self
+            .http
+            .execute(request)
+            .await
+            .map_err(|err| (err.into(), None))?;
+        let status = response.status();
+
+        let limit = RateLimit::from_headers(response.headers());
+        if let (Some(token), Some(limit)) = (token, limit) {
+            self.rate_limits.lock().unwrap().insert(token.key(), limit);
+        }
+
+        let body = response.bytes().await.map_err(|err| (err.into(), limit))?;
+
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err((ClickupError::from_response(status, &body), limit))
+        }
+    }
+
+    /// Sends a request and parses the JSON response.
+    pub(crate) async fn send_json<T: DeserializeOwned>(
+        &self,
+        token: Option<&ClickupToken>,
+        request: RequestBuilder,
+    ) -> Result<T, ClickupError> {
+        let body = self.send(token, request).await?;
+        serde_json::from_slice(&body).map_err(ClickupError::Decode)
+    }
+}
+
+impl Default for ClickupConnection {
+    fn default() -> Self {
+        Self::new(&ClientConfig::default()).expect("default HTTP client can be built")
+    }
+}
+
+/// Makes requests to the ClickUp API on behalf of one token.
+///
+/// The API calls are methods on it, grouped with the types they use in the other `clickup`
+/// modules.
+#[derive(Debug, Clone)]
+pub struct ClickupClient {
+    connection: ClickupConnection,
+    token: ClickupToken,
+}
+
+impl ClickupClient {
+    /// A client with the default settings and its own connection pool.
+    pub fn new(token: ClickupToken) -> Self {
+        ClickupConnection::default().client(token)
+    }
+
+    pub fn connection(&self) -> &ClickupConnection {
+        &self.connection
+    }
+
+    pub(crate) fn token(&self) -> &ClickupToken {
+        &self.token
+    }
+
+    /// The last rate limit ClickUp reported for the token.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.connection.rate_limit(&self.token)
+    }
+
+    /// Starts an authorized request to `path` of the API.
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.connection
+            .request(method, path)
+            .header(reqwest::header::AUTHORIZATION, &self.token.0)
+    }
+
+    /// Starts an authorized request to `path` of version 3 of the API.
+    pub(crate) fn request_v3(&self, method: Method, path: &str) -> RequestBuilder {
+        self.connection
+            .request_v3(method, path)
+            .header(reqwest::header::AUTHORIZATION, &self.token.0)
+    }
+
+    pub(crate) fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub(crate) fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    pub(crate) fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+
+    pub(crate) fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<(), ClickupError> {
+        self.connection
+            .send(Some(&self.token), request)
+            .await
+            .map(|_| ())
+    }
+
+    pub(crate) async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, ClickupError> {
+        self.connection.send_json(Some(&self.token), request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_requests_against_base_url() {
+        let connection = ClickupConnection::new(&ClientConfig {
+            base_url: "http://127.0.0.1:9090/api/v2/".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        let client = connection.client(ClickupToken("pk_test".into()));
+
+        let request = client.get("/task/36w7wbr").build().unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            "http://127.0.0.1:9090/api/v2/task/36w7wbr"
+        );
+        assert_eq!(request.headers()[reqwest::header::AUTHORIZATION], "pk_test");
+
+        let request = client
+            .request_v3(Method::PUT, "/workspaces/20131398/tasks/36w7wbr")
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "http://127.0.0.1:9090/api/v3/workspaces/20131398/tasks/36w7wbr"
+        );
+    }
+
+    #[test]
+    fn keeps_rate_limits_without_the_token() {
+        let connection = ClickupConnection::new(&ClientConfig::default()).unwrap();
+        let token = ClickupToken("pk_4401289_SECRET".into());
+        let limit = RateLimit {
+            remaining: 42,
+            reset: UNIX_EPOCH + Duration::from_secs(1642735012),
+        };
+        connection
+            .rate_limits
+            .lock()
+            .unwrap()
+            .insert(token.key(), limit);
+
+        assert_eq!(connection.rate_limit(&token), Some(limit));
+        assert!(!format!("{connection:?}").contains("SECRET"));
+    }
+
+    #[test]
+    fn encodes_path_segments() {
+        assert_eq!(path_segment("needs QA/review"), "needs%20QA%2Freview");
+        assert_eq!(path_segment("bug-fix_v2"), "bug-fix_v2");
+    }
+
+    #[test]
+    fn reads_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RateLimit::REMAINING_HEADER, "42".parse().unwrap());
+        headers.insert(RateLimit::RESET_HEADER, "1642735012".parse().unwrap());
+
+        assert_eq!(
+            RateLimit::from_headers(&headers),
+            Some(RateLimit {
+                remaining: 42,
+                reset: UNIX_EPOCH + Duration::from_secs(1642735012),
+            })
+        );
+
+        headers.remove(RateLimit::RESET_HEADER);
+        assert_eq!(RateLimit::from_headers(&headers), None);
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let config = ClientConfig {
+            retry_delay_ms: 100,
+            ..Default::default()
+        };
+
+        for retry in 0..4 {
+            let full = Duration::from_millis(100 * 2u64.pow(retry));
+            let delay = config.retry_delay(retry);
+            assert!(
+                delay >= full / 2 && delay < full,
+                "{delay:?} for retry {retry}"
+            );
+        }
+    }
+}
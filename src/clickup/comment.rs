@@ -0,0 +1,380 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    list::ListId,
+    markdown,
+    search::ViewId,
+    string_or_number,
+    task::TaskId,
+    timestamp,
+    user::{User, UserId},
+};
+
+/// Comments have string ids, except in the response to creating one.
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct CommentId(
+    #[serde(deserialize_with = "string_or_number::deserialize")] pub(crate) String,
+);
+
+impl From<&str> for CommentId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for CommentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: CommentId,
+    /// The rich text of the comment.
+    pub comment: Vec<CommentBlock>,
+    /// The comment as plain text.
+    pub comment_text: String,
+    pub user: User,
+    pub assignee: Option<User>,
+    #[serde(default)]
+    pub resolved: bool,
+    #[serde(with = "timestamp")]
+    pub date: DateTime<Utc>,
+}
+
+/// One piece of a comment's rich text, which ClickUp stores as a Quill delta.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommentBlock {
+    /// An @mention notifying the user.
+    Mention {
+        r#type: MentionType,
+        user: Mentioned,
+    },
+    /// Emoji, attachments, task mentions and other embeds, kept as ClickUp sent them.
+    Embed {
+        r#type: String,
+        #[serde(flatten)]
+        rest: Map<String, Value>,
+    },
+    /// Text in the same style. A `"\n"` ends a line, its attributes style the whole line.
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+        attributes: Attributes,
+    },
+}
+
+impl CommentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text {
+            text: text.into(),
+            attributes: Attributes::default(),
+        }
+    }
+
+    pub fn mention(user: UserId) -> Self {
+        Self::Mention {
+            r#type: MentionType::Tag,
+            user: Mentioned { id: user },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MentionType {
+    Tag,
+}
+
+/// The mentioned user, ClickUp also sends their name and picture when reading comments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mentioned {
+    pub id: UserId,
+}
+
+/// The style of a piece of text, or of a line for the attributes of a `"\n"`.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attributes {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strike: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub code: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// Heading level of the line, from 1 to 6.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list: Option<ListAttribute>,
+    /// Nesting of a list item, 0 for top level items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blockquote: Option<Blockquote>,
+    #[serde(rename = "code-block", skip_serializing_if = "Option::is_none")]
+    pub code_block: Option<CodeBlock>,
+}
+
+impl Attributes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListAttribute {
+    pub list: ListKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListKind {
+    Bullet,
+    Ordered,
+    /// A checklist item that is done.
+    Checked,
+    Unchecked,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blockquote {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeBlock {
+    /// Language of the code, `plain` when it has none.
+    #[serde(rename = "code-block")]
+    pub language: String,
+}
+
+/// A comment to post, built from rich text or markdown.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct NewComment {
+    comment: Vec<CommentBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<UserId>,
+    notify_all: bool,
+}
+
+impl NewComment {
+    pub fn new(comment: Vec<CommentBlock>) -> Self {
+        Self {
+            comment,
+            ..Default::default()
+        }
+    }
+
+    /// Converts `markdown` to rich text, see [`markdown::to_comment`] for how users are
+    /// mentioned.
+    pub fn markdown(markdown: &str, users: &[User]) -> Self {
+        Self::new(markdown::to_comment(markdown, users))
+    }
+
+    /// Assigns the comment to a user to resolve it.
+    pub fn assignee(mut self, user: UserId) -> Self {
+        self.assignee = Some(user);
+        self
+    }
+
+    /// Notifies everyone watching the task, not only the mentioned users.
+    pub fn notify_all(mut self) -> Self {
+        self.notify_all = true;
+        self
+    }
+}
+
+/// Changes to a comment, `None` leaves a part of it as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct CommentUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Vec<CommentBlock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<UserId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<bool>,
+}
+
+/// What ClickUp returns for a comment it created.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CreatedComment {
+    pub id: CommentId,
+    #[serde(with = "timestamp")]
+    pub date: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct CommentsResponse {
+    comments: Vec<Comment>,
+}
+
+impl ClickupClient {
+    /// Gets the most recent comments on the task, newest first.
+    pub async fn get_task_comments(&self, task: &TaskId) -> Result<Vec<Comment>, ClickupError> {
+        self.comments(&format!("/task/{}/comment", task.0)).await
+    }
+
+    pub async fn create_task_comment(
+        &self,
+        task: &TaskId,
+        comment: &NewComment,
+    ) -> Result<CreatedComment, ClickupError> {
+        let created = self
+            .send_json(
+                self.post(&format!("/task/{}/comment", task.0))
+                    .json(comment),
+            )
+            .await?;
+        self.connection().own_writes().record(task, "comment");
+        Ok(created)
+    }
+
+    /// Gets the most recent comments on the list's own conversation, newest first.
+    pub async fn get_list_comments(&self, list: &ListId) -> Result<Vec<Comment>, ClickupError> {
+        self.comments(&format!("/list/{list}/comment")).await
+    }
+
+    pub async fn create_list_comment(
+        &self,
+        list: &ListId,
+        comment: &NewComment,
+    ) -> Result<CreatedComment, ClickupError> {
+        self.send_json(self.post(&format!("/list/{list}/comment")).json(comment))
+            .await
+    }
+
+    /// Gets the most recent messages of a chat view, newest first.
+    pub async fn get_view_comments(&self, view: &ViewId) -> Result<Vec<Comment>, ClickupError> {
+        self.comments(&format!("/view/{}/comment", view.0)).await
+    }
+
+    pub async fn create_view_comment(
+        &self,
+        view: &ViewId,
+        comment: &NewComment,
+    ) -> Result<CreatedComment, ClickupError> {
+        self.send_json(
+            self.post(&format!("/view/{}/comment", view.0))
+                .json(comment),
+        )
+        .await
+    }
+
+    /// Gets the replies in the thread of a comment.
+    pub async fn get_threaded_comments(
+        &self,
+        comment: &CommentId,
+    ) -> Result<Vec<Comment>, ClickupError> {
+        self.comments(&format!("/comment/{comment}/reply")).await
+    }
+
+    /// Replies in the thread of a comment.
+    pub async fn create_threaded_comment(
+        &self,
+        comment: &CommentId,
+        reply: &NewComment,
+    ) -> Result<CreatedComment, ClickupError> {
+        self.send_json(self.post(&format!("/comment/{comment}/reply")).json(reply))
+            .await
+    }
+
+    pub async fn update_comment(
+        &self,
+        id: &CommentId,
+        update: &CommentUpdate,
+    ) -> Result<(), ClickupError> {
+        self.send(self.put(&format!("/comment/{id}")).json(update))
+            .await
+    }
+
+    pub async fn delete_comment(&self, id: &CommentId) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/comment/{id}"))).await
+    }
+
+    async fn comments(&self, path: &str) -> Result<Vec<Comment>, ClickupError> {
+        let response: CommentsResponse = self.send_json(self.get(path)).await?;
+
+        Ok(response.comments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_comment() {
+        let comment: Comment = serde_json::from_value(json!({
+            "id": "458315",
+            "comment": [
+                { "text": "Moved under " },
+                { "text": "v2", "attributes": { "bold": true } },
+                { "type": "tag", "text": "@Karel", "user": { "id": 38221385, "username": "Karel" } },
+                { "type": "emoticon", "text": "🎉", "emoticon": { "code": "1f389" } },
+                { "text": "\n", "attributes": { "list": { "list": "bullet" } } }
+            ],
+            "comment_text": "Moved under v2 @Karel 🎉\n",
+            "user": { "id": 38221385, "username": "Karel", "color": "#7b68ee", "profilePicture": null },
+            "resolved": false,
+            "assignee": null,
+            "reactions": [],
+            "date": "1642735012018"
+        }))
+        .unwrap();
+
+        assert_eq!(comment.id, CommentId::from("458315"));
+        assert_eq!(comment.comment[2], CommentBlock::mention(UserId(38221385)));
+        assert!(
+            matches!(&comment.comment[3], CommentBlock::Embed { r#type, .. } if r#type == "emoticon")
+        );
+        assert!(matches!(
+            &comment.comment[4],
+            CommentBlock::Text { attributes, .. } if attributes.list == Some(ListAttribute { list: ListKind::Bullet })
+        ));
+    }
+
+    #[test]
+    fn writes_new_comment() {
+        let comment = NewComment::new(vec![
+            CommentBlock::mention(UserId(38221385)),
+            CommentBlock::text(" please review"),
+        ])
+        .assignee(UserId(38221385));
+
+        assert_eq!(
+            serde_json::to_value(&comment).unwrap(),
+            json!({
+                "comment": [
+                    { "type": "tag", "user": { "id": 38221385 } },
+                    { "text": " please review" }
+                ],
+                "assignee": 38221385,
+                "notify_all": false
+            })
+        );
+    }
+
+    #[test]
+    fn created_comment_has_numeric_id() {
+        let created: CreatedComment = serde_json::from_value(json!({
+            "id": 458315,
+            "hist_id": "26508", "date": 1642735012018i64
+        }))
+        .unwrap();
+
+        assert_eq!(created.id, CommentId::from("458315"));
+    }
+}
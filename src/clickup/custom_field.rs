@@ -0,0 +1,1037 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{client::ClickupClient, error::ClickupError, task::TaskId, timestamp, user::User};
+
+/// A custom field of a task or list, with the configuration and value of its type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawCustomField", into = "RawCustomField")]
+pub struct CustomField {
+    pub id: String,
+    pub name: String,
+    pub required: bool,
+    pub kind: CustomFieldKind,
+    /// Ids of the users or tasks a `users` or `tasks` field held when it was fetched or last
+    /// written, as ClickUp only changes these fields by adding and removing entries.
+    fetched: Vec<Value>,
+}
+
+/// The type of a [`CustomField`] with its `type_config` and value, which ClickUp leaves out when
+/// the field is not set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CustomFieldKind {
+    /// A single option, which ClickUp reports by its `orderindex`.
+    DropDown {
+        options: Vec<DropdownOption>,
+        selected: Option<u64>,
+    },
+    /// Any number of options, by their ids.
+    Labels {
+        options: Vec<LabelOption>,
+        selected: Vec<String>,
+    },
+    Text(Option<String>),
+    ShortText(Option<String>),
+    Number(Option<f64>),
+    Currency {
+        /// ISO 4217 code, such as `EUR`.
+        currency_type: Option<String>,
+        precision: Option<u32>,
+        amount: Option<f64>,
+    },
+    Date(Option<DateTime<Utc>>),
+    Checkbox(bool),
+    Users(Vec<User>),
+    Tasks(Vec<TaskRef>),
+    Url(Option<String>),
+    Email(Option<String>),
+    Phone(Option<String>),
+    /// A rating of up to `count` emojis.
+    Emoji {
+        code_point: Option<String>,
+        count: Option<u32>,
+        rating: Option<u32>,
+    },
+    /// A progress bar, either set by hand or calculated from subtasks or checklists.
+    Progress {
+        automatic: bool,
+        start: Option<f64>,
+        end: Option<f64>,
+        percent_complete: Option<f64>,
+    },
+    Location(Option<Location>),
+    /// Types clicky does not model, such as formulas and buttons.
+    Other {
+        r#type: String,
+        type_config: Value,
+        value: Option<Value>,
+    },
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DropdownOption {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub orderindex: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelOption {
+    pub id: String,
+    pub label: String,
+    pub color: Option<String>,
+}
+
+/// A task referenced by a `tasks` field.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRef {
+    pub id: TaskId,
+    pub name: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub lat: f64,
+    pub lng: f64,
+    pub formatted_address: Option<String>,
+}
+
+/// Returned by the setters of [`CustomField`] when the new value does not fit the field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    WrongType {
+        field: String,
+        expected: &'static str,
+        actual: String,
+    },
+    UnknownOption {
+        field: String,
+        option: String,
+    },
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongType {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "field `{field}` is a {actual} field, expected {expected}"
+            ),
+            Self::UnknownOption { field, option } => {
+                write!(f, "field `{field}` has no option `{option}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl CustomField {
+    /// The type as ClickUp names it, such as `drop_down`.
+    pub fn field_type(&self) -> &str {
+        match &self.kind {
+            CustomFieldKind::DropDown { .. } => "drop_down",
+            CustomFieldKind::Labels { .. } => "labels",
+            CustomFieldKind::Text(_) => "text",
+            CustomFieldKind::ShortText(_) => "short_text",
+            CustomFieldKind::Number(_) => "number",
+            CustomFieldKind::Currency { .. } => "currency",
+            CustomFieldKind::Date(_) => "date",
+            CustomFieldKind::Checkbox(_) => "checkbox",
+            CustomFieldKind::Users(_) => "users",
+            CustomFieldKind::Tasks(_) => "tasks",
+            CustomFieldKind::Url(_) => "url",
+            CustomFieldKind::Email(_) => "email",
+            CustomFieldKind::Phone(_) => "phone",
+            CustomFieldKind::Emoji { .. } => "emoji",
+            CustomFieldKind::Progress {
+                automatic: true, ..
+            } => "automatic_progress",
+            CustomFieldKind::Progress { .. } => "manual_progress",
+            CustomFieldKind::Location(_) => "location",
+            CustomFieldKind::Other { r#type, .. } => r#type,
+        }
+    }
+
+    fn wrong_type(&self, expected: &'static str) -> ValueError {
+        ValueError::WrongType {
+            field: self.name.clone(),
+            expected,
+            actual: self.field_type().to_owned(),
+        }
+    }
+
+    /// The options of a `drop_down` field, empty for other types.
+    pub fn dropdown_options(&self) -> &[DropdownOption] {
+        match &self.kind {
+            CustomFieldKind::DropDown { options, .. } => options,
+            _ => &[],
+        }
+    }
+
+    /// The selected option of a `drop_down` field.
+    pub fn selected_option(&self) -> Option<&DropdownOption> {
+        match &self.kind {
+            CustomFieldKind::DropDown {
+                options,
+                selected: Some(index),
+            } => options.iter().find(|option| option.orderindex == *index),
+            _ => None,
+        }
+    }
+
+    /// Selects the option of a `drop_down` field with the given id or name, or clears it.
+    pub fn select_option(&mut self, option: Option<&str>) -> Result<(), ValueError> {
+        let name = self.name.clone();
+        let CustomFieldKind::DropDown { options, selected } = &mut self.kind else {
+            return Err(self.wrong_type("drop_down"));
+        };
+
+        *selected = match option {
+            Some(option) => Some(
+                options
+                    .iter()
+                    .find(|o| o.id == option)
+                    .or_else(|| options.iter().find(|o| o.name == option))
+                    .ok_or_else(|| ValueError::UnknownOption {
+                        field: name,
+                        option: option.to_owned(),
+                    })?
+                    .orderindex,
+            ),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// The selected options of a `labels` field.
+    pub fn selected_labels(&self) -> Vec<&LabelOption> {
+        match &self.kind {
+            CustomFieldKind::Labels { options, selected } => options
+                .iter()
+                .filter(|option| selected.contains(&option.id))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Selects the options of a `labels` field with the given ids or labels.
+    pub fn select_labels(&mut self, labels: &[&str]) -> Result<(), ValueError> {
+        let name = self.name.clone();
+        let CustomFieldKind::Labels { options, selected } = &mut self.kind else {
+            return Err(self.wrong_type("labels"));
+        };
+
+        *selected = labels
+            .iter()
+            .map(|label| {
+                options
+                    .iter()
+                    .find(|o| o.id == *label || o.label == *label)
+                    .map(|o| o.id.clone())
+                    .ok_or_else(|| ValueError::UnknownOption {
+                        field: name.clone(),
+                        option: (*label).to_owned(),
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// The value of the text like types: `text`, `short_text`, `url`, `email` and `phone`.
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            CustomFieldKind::Text(text)
+            | CustomFieldKind::ShortText(text)
+            | CustomFieldKind::Url(text)
+            | CustomFieldKind::Email(text)
+            | CustomFieldKind::Phone(text) => text.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn set_text(&mut self, value: Option<String>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Text(text)
+            | CustomFieldKind::ShortText(text)
+            | CustomFieldKind::Url(text)
+            | CustomFieldKind::Email(text)
+            | CustomFieldKind::Phone(text) => {
+                *text = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("a text field")),
+        }
+    }
+
+    /// The value of the numeric types: `number`, `currency`, `emoji` and the progress types.
+    pub fn number(&self) -> Option<f64> {
+        match &self.kind {
+            CustomFieldKind::Number(number) => *number,
+            CustomFieldKind::Currency { amount, .. } => *amount,
+            CustomFieldKind::Emoji { rating, .. } => rating.map(f64::from),
+            CustomFieldKind::Progress {
+                percent_complete, ..
+            } => *percent_complete,
+            _ => None,
+        }
+    }
+
+    /// Sets a `number`, `currency`, `emoji` or `manual_progress` field.
+    pub fn set_number(&mut self, value: Option<f64>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Number(number) => *number = value,
+            CustomFieldKind::Currency { amount, .. } => *amount = value,
+            CustomFieldKind::Emoji { rating, .. } => *rating = value.map(|value| value as u32),
+            CustomFieldKind::Progress {
+                automatic: false,
+                percent_complete,
+                ..
+            } => *percent_complete = value,
+            _ => return Err(self.wrong_type("a numeric field")),
+        }
+        Ok(())
+    }
+
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        match &self.kind {
+            CustomFieldKind::Date(date) => *date,
+            _ => None,
+        }
+    }
+
+    pub fn set_date(&mut self, value: Option<DateTime<Utc>>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Date(date) => {
+                *date = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("date")),
+        }
+    }
+
+    pub fn checked(&self) -> Option<bool> {
+        match &self.kind {
+            CustomFieldKind::Checkbox(checked) => Some(*checked),
+            _ => None,
+        }
+    }
+
+    pub fn set_checked(&mut self, value: bool) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Checkbox(checked) => {
+                *checked = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("checkbox")),
+        }
+    }
+
+    pub fn users(&self) -> &[User] {
+        match &self.kind {
+            CustomFieldKind::Users(users) => users,
+            _ => &[],
+        }
+    }
+
+    pub fn set_users(&mut self, value: Vec<User>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Users(users) => {
+                *users = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("users")),
+        }
+    }
+
+    pub fn tasks(&self) -> &[TaskRef] {
+        match &self.kind {
+            CustomFieldKind::Tasks(tasks) => tasks,
+            _ => &[],
+        }
+    }
+
+    pub fn set_tasks(&mut self, value: Vec<TaskRef>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Tasks(tasks) => {
+                *tasks = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("tasks")),
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match &self.kind {
+            CustomFieldKind::Location(location) => location.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn set_location(&mut self, value: Option<Location>) -> Result<(), ValueError> {
+        match &mut self.kind {
+            CustomFieldKind::Location(location) => {
+                *location = value;
+                Ok(())
+            }
+            _ => Err(self.wrong_type("location")),
+        }
+    }
+
+    /// The value in the shape `POST /task/{id}/field/{field_id}` expects, `None` when the field
+    /// is not set.
+    ///
+    /// `users` and `tasks` fields add their users and tasks, and remove those the field held when
+    /// it was fetched or last written but no longer does.
+    pub fn write_value(&self) -> Option<Value> {
+        match &self.kind {
+            CustomFieldKind::DropDown { .. } => self
+                .selected_option()
+                .map(|option| option.id.clone().into()),
+            CustomFieldKind::Labels { selected, .. } => {
+                (!selected.is_empty()).then(|| json!(selected))
+            }
+            CustomFieldKind::Text(text)
+            | CustomFieldKind::ShortText(text)
+            | CustomFieldKind::Url(text)
+            | CustomFieldKind::Email(text)
+            | CustomFieldKind::Phone(text) => text.clone().map(Value::from),
+            CustomFieldKind::Number(number) => number.map(Value::from),
+            CustomFieldKind::Currency { amount, .. } => amount.map(Value::from),
+            CustomFieldKind::Date(date) => date.map(|date| date.timestamp_millis().into()),
+            CustomFieldKind::Checkbox(checked) => Some((*checked).into()),
+            CustomFieldKind::Users(_) | CustomFieldKind::Tasks(_) => {
+                let add = entry_ids(&self.kind);
+                let rem: Vec<_> = self.fetched.iter().filter(|id| !add.contains(id)).collect();
+                (!add.is_empty()).then(|| json!({ "add": add, "rem": rem }))
+            }
+            CustomFieldKind::Emoji { rating, .. } => rating.map(Value::from),
+            CustomFieldKind::Progress {
+                percent_complete, ..
+            } => percent_complete.map(|percent| json!({ "current": percent })),
+            CustomFieldKind::Location(location) => location.as_ref().map(|location| {
+                json!({
+                    "location": { "lat": location.lat, "lng": location.lng },
+                    "formatted_address": location.formatted_address,
+                })
+            }),
+            CustomFieldKind::Other { value, .. } => value.clone(),
+        }
+    }
+}
+
+impl ClickupClient {
+    /// Writes the current value of `field` to the task, clearing the field when it has no value.
+    ///
+    /// Once written, the value is what later writes of `field` add to and remove from.
+    pub async fn set_custom_field_value(
+        &self,
+        task: &TaskId,
+        field: &mut CustomField,
+    ) -> Result<(), ClickupError> {
+        let result = self.write_custom_field_value(task, field).await;
+        if result.is_ok() {
+            field.fetched = entry_ids(&field.kind);
+        }
+        result
+    }
+
+    async fn write_custom_field_value(
+        &self,
+        task: &TaskId,
+        field: &CustomField,
+    ) -> Result<(), ClickupError> {
+        let Some(value) = field.write_value() else {
+            return self.remove_custom_field_value(task, &field.id).await;
+        };
+
+        let mut body = json!({ "value": value });
+        if let CustomFieldKind::Date(_) = field.kind {
+            body["value_options"] = json!({ "time": true });
+        }
+
+        let result = self
+            .send(
+                self.post(&format!("/task/{}/field/{}", task.0, field.id))
+                    .json(&body),
+            )
+            .await;
+        self.connection().tasks().invalidate(task);
+        if result.is_ok() {
+            self.connection().own_writes().record(task, "custom_field");
+        }
+        result
+    }
+
+    /// Clears the value of the custom field `field_id` on the task.
+    pub async fn remove_custom_field_value(
+        &self,
+        task: &TaskId,
+        field_id: &str,
+    ) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.delete(&format!("/task/{}/field/{}", task.0, field_id)))
+            .await;
+        self.connection().tasks().invalidate(task);
+        if result.is_ok() {
+            self.connection().own_writes().record(task, "custom_field");
+        }
+        result
+    }
+}
+
+/// A custom field as ClickUp sends it, before its `type_config` and `value` are read by type.
+#[derive(Clone, Serialize, Deserialize)]
+struct RawCustomField {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    r#type: String,
+    #[serde(default)]
+    type_config: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+    #[serde(default)]
+    required: Option<bool>,
+}
+
+/// A number that ClickUp may send as a string.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn string(value: Option<Value>) -> Option<String> {
+    match value? {
+        Value::String(s) if s.is_empty() => None,
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn config<T: for<'de> Deserialize<'de> + Default>(config: &Value) -> serde_json::Result<T> {
+    match config {
+        Value::Null => Ok(T::default()),
+        config => T::deserialize(config),
+    }
+}
+
+impl TryFrom<RawCustomField> for CustomField {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawCustomField) -> Result<Self, Self::Error> {
+        #[derive(Default, Deserialize)]
+        struct Options<T> {
+            #[serde(default = "Vec::new")]
+            options: Vec<T>,
+        }
+
+        #[derive(Default, Deserialize)]
+        struct CurrencyConfig {
+            currency_type: Option<String>,
+            precision: Option<u32>,
+        }
+
+        #[derive(Default, Deserialize)]
+        struct EmojiConfig {
+            code_point: Option<String>,
+            count: Option<u32>,
+        }
+
+        #[derive(Default, Deserialize)]
+        struct ProgressConfig {
+            start: Option<f64>,
+            end: Option<f64>,
+        }
+
+        #[derive(Deserialize)]
+        struct LocationValue {
+            location: Coordinates,
+            formatted_address: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Coordinates {
+            lat: f64,
+            lng: f64,
+        }
+
+        let value = raw.value.filter(|value| !value.is_null());
+
+        let kind = match raw.r#type.as_str() {
+            "drop_down" => CustomFieldKind::DropDown {
+                options: config::<Options<DropdownOption>>(&raw.type_config)?.options,
+                selected: value.as_ref().and_then(number).map(|index| index as u64),
+            },
+            "labels" => CustomFieldKind::Labels {
+                options: config::<Options<LabelOption>>(&raw.type_config)?.options,
+                selected: value
+                    .map(serde_json::from_value)
+                    .transpose()?
+                    .unwrap_or_default(),
+            },
+            "text" => CustomFieldKind::Text(string(value)),
+            "short_text" => CustomFieldKind::ShortText(string(value)),
+            "url" => CustomFieldKind::Url(string(value)),
+            "email" => CustomFieldKind::Email(string(value)),
+            "phone" => CustomFieldKind::Phone(string(value)),
+            "number" => CustomFieldKind::Number(value.as_ref().and_then(number)),
+            "currency" => {
+                let currency: CurrencyConfig = config(&raw.type_config)?;
+                CustomFieldKind::Currency {
+                    currency_type: currency.currency_type,
+                    precision: currency.precision,
+                    amount: value.as_ref().and_then(number),
+                }
+            }
+            "date" => CustomFieldKind::Date(match value {
+                Some(value) => timestamp::option::deserialize(value)?,
+                None => None,
+            }),
+            "checkbox" => CustomFieldKind::Checkbox(match value {
+                Some(Value::Bool(checked)) => checked,
+                Some(Value::String(checked)) => checked == "true",
+                _ => false,
+            }),
+            "users" => CustomFieldKind::Users(
+                value
+                    .map(serde_json::from_value)
+                    .transpose()?
+                    .unwrap_or_default(),
+            ),
+            "tasks" => CustomFieldKind::Tasks(
+                value
+                    .map(serde_json::from_value)
+                    .transpose()?
+                    .unwrap_or_default(),
+            ),
+            "emoji" => {
+                let emoji: EmojiConfig = config(&raw.type_config)?;
+                CustomFieldKind::Emoji {
+                    code_point: emoji.code_point,
+                    count: emoji.count,
+                    rating: value.as_ref().and_then(number).map(|n| n as u32),
+                }
+            }
+            "automatic_progress" | "manual_progress" => {
+                let progress: ProgressConfig = config(&raw.type_config)?;
+                CustomFieldKind::Progress {
+                    automatic: raw.r#type == "automatic_progress",
+                    start: progress.start,
+                    end: progress.end,
+                    percent_complete: value
+                        .as_ref()
+                        .and_then(|value| value.get("percent_complete"))
+                        .and_then(number),
+                }
+            }
+            "location" => CustomFieldKind::Location(
+                value
+                    .map(serde_json::from_value::<LocationValue>)
+                    .transpose()?
+                    .map(|value| Location {
+                        lat: value.location.lat,
+                        lng: value.location.lng,
+                        formatted_address: value.formatted_address,
+                    }),
+            ),
+            _ => CustomFieldKind::Other {
+                r#type: raw.r#type,
+                type_config: raw.type_config,
+                value,
+            },
+        };
+
+        Ok(Self {
+            id: raw.id,
+            name: raw.name,
+            required: raw.required.unwrap_or(false),
+            fetched: entry_ids(&kind),
+            kind,
+        })
+    }
+}
+
+/// The ids of the users or tasks in a `users` or `tasks` field, as written to ClickUp.
+fn entry_ids(kind: &CustomFieldKind) -> Vec<Value> {
+    match kind {
+        CustomFieldKind::Users(users) => users.iter().map(|user| json!(user.id)).collect(),
+        CustomFieldKind::Tasks(tasks) => tasks.iter().map(|task| json!(task.id)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl From<CustomField> for RawCustomField {
+    fn from(field: CustomField) -> Self {
+        let r#type = field.field_type().to_owned();
+
+        let (type_config, value) = match field.kind {
+            CustomFieldKind::DropDown { options, selected } => {
+                (json!({ "options": options }), selected.map(Value::from))
+            }
+            CustomFieldKind::Labels { options, selected } => (
+                json!({ "options": options }),
+                (!selected.is_empty()).then(|| json!(selected)),
+            ),
+            CustomFieldKind::Text(text)
+            | CustomFieldKind::ShortText(text)
+            | CustomFieldKind::Url(text)
+            | CustomFieldKind::Email(text)
+            | CustomFieldKind::Phone(text) => (json!({}), text.map(Value::from)),
+            CustomFieldKind::Number(number) => (json!({}), number.map(Value::from)),
+            CustomFieldKind::Currency {
+                currency_type,
+                precision,
+                amount,
+            } => (
+                json!({ "currency_type": currency_type, "precision": precision }),
+                amount.map(Value::from),
+            ),
+            CustomFieldKind::Date(date) => (
+                json!({}),
+                date.map(|date| date.timestamp_millis().to_string().into()),
+            ),
+            CustomFieldKind::Checkbox(checked) => (json!({}), Some(checked.into())),
+            CustomFieldKind::Users(users) => (json!({}), (!users.is_empty()).then(|| json!(users))),
+            CustomFieldKind::Tasks(tasks) => (json!({}), (!tasks.is_empty()).then(|| json!(tasks))),
+            CustomFieldKind::Emoji {
+                code_point,
+                count,
+                rating,
+            } => (
+                json!({ "code_point": code_point, "count": count }),
+                rating.map(Value::from),
+            ),
+            CustomFieldKind::Progress {
+                automatic: _,
+                start,
+                end,
+                percent_complete,
+            } => (
+                json!({ "start": start, "end": end }),
+                percent_complete.map(|percent| json!({ "percent_complete": percent })),
+            ),
+            CustomFieldKind::Location(location) => (
+                json!({}),
+                location.map(|location| {
+                    json!({
+                        "location": { "lat": location.lat, "lng": location.lng },
+                        "formatted_address": location.formatted_address,
+                    })
+                }),
+            ),
+            CustomFieldKind::Other {
+                type_config, value, ..
+            } => (type_config, value),
+        };
+
+        Self {
+            id: field.id,
+            name: field.name,
+            r#type,
+            type_config,
+            value,
+            required: Some(field.required),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn field(r#type: &str, type_config: Value, value: Value) -> CustomField {
+        serde_json::from_value(json!({
+            "id": "5dc86497-098d-4bb0-87d6-cf28e43812e7",
+            "name": "Field",
+            "type": r#type,
+            "type_config": type_config,
+            "date_created": "1642734938456",
+            "hide_from_guests": false,
+            "value": value,
+            "required": false
+        }))
+        .unwrap()
+    }
+
+    fn dropdown(value: Value) -> CustomField {
+        field(
+            "drop_down",
+            json!({
+                "default": 0,
+                "placeholder": null,
+                "options": [
+                    { "id": "a1", "name": "v0", "color": null, "orderindex": 0 },
+                    { "id": "b2", "name": "v1", "color": "#04A9F4", "orderindex": 1 }
+                ]
+            }),
+            value,
+        )
+    }
+
+    #[test]
+    fn selects_dropdown_options() {
+        let mut field = dropdown(json!(1));
+        assert_eq!(field.selected_option().unwrap().name, "v1");
+
+        field.select_option(Some("v0")).unwrap();
+        assert_eq!(field.selected_option().unwrap().id, "a1");
+
+        assert_eq!(
+            field.select_option(Some("v9")),
+            Err(ValueError::UnknownOption {
+                field: "Field".into(),
+                option: "v9".into()
+            })
+        );
+        assert_eq!(
+            field.set_checked(true),
+            Err(ValueError::WrongType {
+                field: "Field".into(),
+                expected: "checkbox",
+                actual: "drop_down".into()
+            })
+        );
+
+        assert_eq!(dropdown(Value::Null).selected_option(), None);
+    }
+
+    #[test]
+    fn reads_values_by_type() {
+        let labels = field(
+            "labels",
+            json!({ "options": [
+                { "id": "l1", "label": "backend", "color": null },
+                { "id": "l2", "label": "frontend", "color": null }
+            ]}),
+            json!(["l2"]),
+        );
+        assert_eq!(labels.selected_labels()[0].label, "frontend");
+
+        assert_eq!(
+            field("number", json!({}), json!("12.5")).number(),
+            Some(12.5)
+        );
+        assert_eq!(
+            field(
+                "currency",
+                json!({ "precision": 2, "currency_type": "EUR" }),
+                json!(3)
+            )
+            .number(),
+            Some(3.0)
+        );
+        assert_eq!(
+            field("date", json!({}), json!("1643007600000")).date(),
+            Some(Utc.timestamp_millis_opt(1643007600000).unwrap())
+        );
+        assert_eq!(
+            field("checkbox", json!({}), json!("true")).checked(),
+            Some(true)
+        );
+        assert_eq!(
+            field("checkbox", json!({}), Value::Null).checked(),
+            Some(false)
+        );
+        assert_eq!(
+            field("url", json!({}), json!("https://clickup.com")).text(),
+            Some("https://clickup.com")
+        );
+        assert_eq!(
+            field(
+                "tasks",
+                json!({}),
+                json!([{ "id": "36w7wbr", "name": "Task" }])
+            )
+            .tasks()[0]
+                .id,
+            TaskId::from("36w7wbr")
+        );
+        assert_eq!(
+            field(
+                "manual_progress",
+                json!({ "start": 0, "end": 100 }),
+                json!({ "percent_complete": 40 })
+            )
+            .number(),
+            Some(40.0)
+        );
+        assert_eq!(
+            field(
+                "location",
+                json!({}),
+                json!({ "location": { "lat": 51.05, "lng": 3.72 }, "formatted_address": "Ghent" })
+            )
+            .location()
+            .unwrap()
+            .formatted_address
+            .as_deref(),
+            Some("Ghent")
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_types() {
+        let formula = field("formula", json!({ "formula": "1 + 1" }), json!(2));
+
+        assert_eq!(formula.field_type(), "formula");
+        assert_eq!(
+            serde_json::from_value::<CustomField>(serde_json::to_value(&formula).unwrap()).unwrap(),
+            formula
+        );
+    }
+
+    #[test]
+    fn writes_values_by_type() {
+        let mut field = dropdown(Value::Null);
+        assert_eq!(field.write_value(), None);
+        field.select_option(Some("v1")).unwrap();
+        assert_eq!(field.write_value(), Some(json!("b2")));
+
+        let mut url = field_of("url");
+        url.set_text(Some("https://github.com/clicky/pull/1".into()))
+            .unwrap();
+        assert_eq!(
+            url.write_value(),
+            Some(json!("https://github.com/clicky/pull/1"))
+        );
+
+        let mut date = field_of("date");
+        date.set_date(Some(Utc.timestamp_millis_opt(1643007600000).unwrap()))
+            .unwrap();
+        assert_eq!(date.write_value(), Some(json!(1643007600000i64)));
+
+        let mut users = field_of("users");
+        users
+            .set_users(vec![User {
+                id: 38221385.into(),
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(
+            users.write_value(),
+            Some(json!({ "add": [38221385], "rem": [] }))
+        );
+
+        assert_eq!(field_of("checkbox").write_value(), Some(json!(false)));
+    }
+
+    #[test]
+    fn writes_removed_users_and_tasks() {
+        let user = |id: u64| User {
+            id: id.into(),
+            ..Default::default()
+        };
+        let mut users = field("users", json!({}), json!([user(38221385), user(4401289)]));
+        users.set_users(vec![user(4401289), user(5528710)]).unwrap();
+        assert_eq!(
+            users.write_value(),
+            Some(json!({ "add": [4401289, 5528710], "rem": [38221385] }))
+        );
+
+        let mut tasks = field("tasks", json!({}), json!([{ "id": "36w7wbr" }]));
+        tasks
+            .set_tasks(vec![TaskRef {
+                id: TaskId::from("36w83z6"),
+                name: None,
+            }])
+            .unwrap();
+        assert_eq!(
+            tasks.write_value(),
+            Some(json!({ "add": ["36w83z6"], "rem": ["36w7wbr"] }))
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_against_the_last_written_value() {
+        use std::sync::{Arc, Mutex};
+
+        use axum::{extract::Json, routing::post, Extension, Router};
+
+        use crate::clickup::{
+            auth::ClickupToken,
+            client::{ClickupConnection, ClientConfig},
+        };
+
+        let bodies = Arc::new(Mutex::new(Vec::<Value>::new()));
+        let app = Router::new()
+            .route(
+                "/api/v2/task/:task/field/:field",
+                post(
+                    |Extension(bodies): Extension<Arc<Mutex<Vec<Value>>>>,
+                     Json(body): Json<Value>| async move {
+                        bodies.lock().unwrap().push(body);
+                        Json(json!({}))
+                    },
+                ),
+            )
+            .layer(Extension(bodies.clone()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        let client = ClickupConnection::new(&ClientConfig {
+            base_url: format!("http://{addr}/api/v2"),
+            ..Default::default()
+        })
+        .unwrap()
+        .client(ClickupToken("pk_test".into()));
+
+        let user = |id: u64| User {
+            id: id.into(),
+            ..Default::default()
+        };
+        let task = TaskId::from("36w7wbr");
+        let mut users = field("users", json!({}), json!([user(38221385)]));
+
+        users.set_users(vec![user(4401289)]).unwrap();
+        client
+            .set_custom_field_value(&task, &mut users)
+            .await
+            .unwrap();
+        users.set_users(vec![user(38221385)]).unwrap();
+        client
+            .set_custom_field_value(&task, &mut users)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *bodies.lock().unwrap(),
+            [
+                json!({ "value": { "add": [4401289], "rem": [38221385] } }),
+                json!({ "value": { "add": [38221385], "rem": [4401289] } }),
+            ]
+        );
+    }
+
+    fn field_of(r#type: &str) -> CustomField {
+        field(r#type, json!({}), Value::Null)
+    }
+
+    #[test]
+    fn roundtrips_typed_fields() {
+        let mut field = dropdown(json!(0));
+        field.select_option(Some("b2")).unwrap();
+
+        let json = serde_json::to_value(&field).unwrap();
+        assert_eq!(json["type"], "drop_down");
+        assert_eq!(json["value"], 1);
+        assert_eq!(serde_json::from_value::<CustomField>(json).unwrap(), field);
+    }
+}
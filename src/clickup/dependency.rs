@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    task::{Task, TaskId},
+    timestamp,
+    user::UserId,
+};
+
+/// `task_id` waits on `depends_on`, the same entry shows up on both tasks.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub task_id: TaskId,
+    pub depends_on: TaskId,
+    #[serde(default, with = "timestamp::option")]
+    pub date_created: Option<DateTime<Utc>>,
+    pub userid: Option<UserId>,
+}
+
+/// A plain link between two tasks, without an order.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedTask {
+    pub task_id: TaskId,
+    pub link_id: TaskId,
+    #[serde(default, with = "timestamp::option")]
+    pub date_created: Option<DateTime<Utc>>,
+    pub userid: Option<UserId>,
+}
+
+/// How a task relates to the other task of a dependency, as the ClickUp UI calls it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// The task waits on the other task.
+    WaitingOn(TaskId),
+    /// The other task waits on the task.
+    Blocking(TaskId),
+}
+
+impl DependencyKind {
+    fn param(&self) -> (&'static str, &TaskId) {
+        match self {
+            Self::WaitingOn(other) => ("depends_on", other),
+            Self::Blocking(other) => ("dependency_of", other),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TaskResponse {
+    task: Task,
+}
+
+impl ClickupClient {
+    pub async fn add_dependency(
+        &self,
+        task: &TaskId,
+        dependency: &DependencyKind,
+    ) -> Result<(), ClickupError> {
+        let (name, other) = dependency.param();
+
+        let result = self
+            .send(
+                self.post(&format!("/task/{}/dependency", task.0))
+                    .json(&HashMap::from([(name, other)])),
+            )
+            .await;
+        self.invalidate_pair(task, other);
+        result
+    }
+
+    pub async fn remove_dependency(
+        &self,
+        task: &TaskId,
+        dependency: &DependencyKind,
+    ) -> Result<(), ClickupError> {
+        let (name, other) = dependency.param();
+
+        let result = self
+            .send(
+                self.delete(&format!("/task/{}/dependency", task.0))
+                    .query(&[(name, &other.0)]),
+            )
+            .await;
+        self.invalidate_pair(task, other);
+        result
+    }
+
+    /// Links the two tasks, returning `task` with the new link.
+    pub async fn add_task_link(
+        &self,
+        task: &TaskId,
+        links_to: &TaskId,
+    ) -> Result<Task, ClickupError> {
+        let response: Result<TaskResponse, _> = self
+            .send_json(self.post(&format!("/task/{}/link/{}", task.0, links_to.0)))
+            .await;
+        self.invalidate_pair(task, links_to);
+        Ok(response?.task)
+    }
+
+    /// Removes the link between the two tasks, returning `task` without it.
+    pub async fn remove_task_link(
+        &self,
+        task: &TaskId,
+        links_to: &TaskId,
+    ) -> Result<Task, ClickupError> {
+        let response: Result<TaskResponse, _> = self
+            .send_json(self.delete(&format!("/task/{}/link/{}", task.0, links_to.0)))
+            .await;
+        self.invalidate_pair(task, links_to);
+        Ok(response?.task)
+    }
+
+    /// Both tasks of a dependency or link list it.
+    fn invalidate_pair(&self, task: &TaskId, other: &TaskId) {
+        let cache = self.connection().tasks();
+        cache.invalidate(task);
+        cache.invalidate(other);
+    }
+}
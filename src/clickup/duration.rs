@@ -0,0 +1,66 @@
+//! (De)serializes the millisecond durations ClickUp uses, such as time estimates, for
+//! `#[serde(with = "...")]`.
+//!
+//! Like timestamps they come as numbers in some places and as strings in others.
+
+use chrono::Duration;
+use serde::{Deserializer, Serializer};
+
+use super::string_or_number;
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(duration.num_milliseconds())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    string_or_number::deserialize(deserializer).map(Duration::milliseconds)
+}
+
+/// For optional durations, which ClickUp sends as `null` when unset.
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => super::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let millis = string_or_number::option::deserialize(deserializer)?;
+        Ok(millis.map(Duration::milliseconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Estimate {
+        #[serde(with = "super")]
+        spent: Duration,
+        #[serde(default, with = "super::option")]
+        estimate: Option<Duration>,
+    }
+
+    #[test]
+    fn reads_strings_and_numbers() {
+        let estimate: Estimate =
+            serde_json::from_str(r#"{"spent":"3600000","estimate":5400000}"#).unwrap();
+
+        assert_eq!(estimate.spent, Duration::hours(1));
+        assert_eq!(estimate.estimate, Some(Duration::minutes(90)));
+
+        let estimate: Estimate = serde_json::from_str(r#"{"spent":0,"estimate":null}"#).unwrap();
+        assert_eq!(estimate.estimate, None);
+    }
+}
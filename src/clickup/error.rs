@@ -0,0 +1,183 @@
+use std::fmt;
+
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+
+/// The body ClickUp sends with a failed request, such as
+/// `{"err": "Token invalid", "ECODE": "OAUTH_025"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ErrorBody {
+    pub err: String,
+    #[serde(rename = "ECODE")]
+    pub ecode: Option<String>,
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.ecode {
+            Some(ecode) => write!(f, "{} ({})", self.err, ecode),
+            None => f.write_str(&self.err),
+        }
+    }
+}
+
+/// A failed request to the ClickUp API.
+#[derive(Debug)]
+pub enum ClickupError {
+    /// The token is invalid, expired or not authorized for the workspace (401 and 403).
+    Unauthorized(ErrorBody),
+    NotFound(ErrorBody),
+    /// Too many requests were made with the token (429).
+    RateLimited(ErrorBody),
+    /// ClickUp rejected the request itself, such as a missing field or an invalid value (400 and
+    /// 422).
+    Validation(ErrorBody),
+    /// Any other error status, usually a problem on ClickUp's side.
+    Status {
+        status: StatusCode,
+        body: ErrorBody,
+    },
+    /// ClickUp could not be reached or the connection failed.
+    Transport(reqwest::Error),
+    /// A successful response that does not match the expected type.
+    Decode(serde_json::Error),
+}
+
+impl ClickupError {
+    /// Classifies an error response by its status, keeping ClickUp's message.
+    pub fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        let body = serde_json::from_slice(body).unwrap_or_else(|_| ErrorBody {
+            err: String::from_utf8_lossy(body).trim().to_owned(),
+            ecode: None,
+        });
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized(body),
+            StatusCode::NOT_FOUND => Self::NotFound(body),
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited(body),
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Self::Validation(body),
+            status => Self::Status { status, body },
+        }
+    }
+
+    /// Whether the same request may succeed later, unlike requests ClickUp refused.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited(_) | Self::Transport(_) => true,
+            Self::Status { status, .. } => status.is_server_error(),
+            Self::Unauthorized(_) | Self::NotFound(_) | Self::Validation(_) | Self::Decode(_) => {
+                false
+            }
+        }
+    }
+
+    /// Whether a request with `method` that failed this way may be sent again as it is.
+    ///
+    /// A request that failed in transport or on ClickUp's side may still have been carried out, so
+    /// only requests that do the same when repeated are retried then. A `POST` creating a task,
+    /// comment or webhook would create it twice, it is only retried when rate limited.
+    pub fn is_retryable_request(&self, method: &Method) -> bool {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE => {
+                self.is_retryable()
+            }
+            _ => matches!(self, Self::RateLimited(_)),
+        }
+    }
+
+    /// ClickUp's `ECODE` for the error, when it sent one.
+    pub fn ecode(&self) -> Option<&str> {
+        match self {
+            Self::Unauthorized(body)
+            | Self::NotFound(body)
+            | Self::RateLimited(body)
+            | Self::Validation(body)
+            | Self::Status { body, .. } => body.ecode.as_deref(),
+            Self::Transport(_) | Self::Decode(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ClickupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unauthorized(body) => write!(f, "unauthorized: {body}"),
+            Self::NotFound(body) => write!(f, "not found: {body}"),
+            Self::RateLimited(body) => write!(f, "rate limited: {body}"),
+            Self::Validation(body) => write!(f, "invalid request: {body}"),
+            Self::Status { status, body } => write!(f, "ClickUp responded {status}: {body}"),
+            Self::Transport(err) => write!(f, "request to ClickUp failed: {err}"),
+            Self::Decode(err) => write!(f, "unexpected response from ClickUp: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ClickupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClickupError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_clickup_error_body() {
+        let err = ClickupError::from_response(
+            StatusCode::UNAUTHORIZED,
+            br#"{"err":"Token invalid","ECODE":"OAUTH_025"}"#,
+        );
+
+        assert!(matches!(&err, ClickupError::Unauthorized(body) if body.err == "Token invalid"));
+        assert_eq!(err.ecode(), Some("OAUTH_025"));
+        assert_eq!(err.to_string(), "unauthorized: Token invalid (OAUTH_025)");
+    }
+
+    #[test]
+    fn classifies_by_status() {
+        let from = |status| ClickupError::from_response(status, b"Bad Gateway");
+
+        assert!(matches!(
+            from(StatusCode::NOT_FOUND),
+            ClickupError::NotFound(_)
+        ));
+        assert!(!from(StatusCode::NOT_FOUND).is_retryable());
+        assert!(matches!(
+            from(StatusCode::TOO_MANY_REQUESTS),
+            ClickupError::RateLimited(_)
+        ));
+        assert!(matches!(
+            from(StatusCode::BAD_REQUEST),
+            ClickupError::Validation(_)
+        ));
+        assert!(matches!(
+            from(StatusCode::BAD_GATEWAY),
+            ClickupError::Status { status: StatusCode::BAD_GATEWAY, body } if body.err == "Bad Gateway"
+        ));
+        assert!(from(StatusCode::BAD_GATEWAY).is_retryable());
+    }
+
+    #[test]
+    fn retries_only_idempotent_requests_after_failures() {
+        let from = |status| ClickupError::from_response(status, b"");
+
+        for method in [Method::GET, Method::PUT, Method::DELETE] {
+            assert!(from(StatusCode::BAD_GATEWAY).is_retryable_request(&method));
+            assert!(from(StatusCode::TOO_MANY_REQUESTS).is_retryable_request(&method));
+        }
+        assert!(!from(StatusCode::BAD_GATEWAY).is_retryable_request(&Method::POST));
+        assert!(!from(StatusCode::NOT_FOUND).is_retryable_request(&Method::POST));
+        assert!(from(StatusCode::TOO_MANY_REQUESTS).is_retryable_request(&Method::POST));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    list::List,
+    space::{SpaceId, SpaceRef},
+    task::Status,
+};
+
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct FolderId(pub(crate) String);
+
+impl From<&str> for FolderId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for FolderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The folder something is in, as tasks and lists refer to it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FolderRef {
+    pub id: FolderId,
+    pub name: Option<String>,
+    /// Lists outside of a folder are in a hidden one.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: FolderId,
+    pub name: String,
+    pub orderindex: Option<f64>,
+    /// Whether the folder has its own `statuses` instead of the space's.
+    #[serde(default)]
+    pub override_statuses: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    pub space: SpaceRef,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub statuses: Vec<Status>,
+    #[serde(default)]
+    pub lists: Vec<List>,
+}
+
+#[derive(Serialize)]
+struct FolderParams<'a> {
+    name: &'a str,
+}
+
+#[derive(Deserialize)]
+struct FoldersResponse {
+    folders: Vec<Folder>,
+}
+
+impl ClickupClient {
+    /// Gets the folders of the space, the archived ones instead of the others if `archived`.
+    pub async fn get_folders(
+        &self,
+        space: &SpaceId,
+        archived: bool,
+    ) -> Result<Vec<Folder>, ClickupError> {
+        let response: FoldersResponse = self
+            .send_json(
+                self.get(&format!("/space/{space}/folder"))
+                    .query(&[("archived", archived)]),
+            )
+            .await?;
+
+        Ok(response.folders)
+    }
+
+    pub async fn get_folder(&self, id: &FolderId) -> Result<Folder, ClickupError> {
+        self.send_json(self.get(&format!("/folder/{id}"))).await
+    }
+
+    pub async fn create_folder(&self, space: &SpaceId, name: &str) -> Result<Folder, ClickupError> {
+        self.send_json(
+            self.post(&format!("/space/{space}/folder"))
+                .json(&FolderParams { name }),
+        )
+        .await
+    }
+
+    /// Renames the folder, the only setting ClickUp lets us change.
+    pub async fn update_folder(&self, id: &FolderId, name: &str) -> Result<Folder, ClickupError> {
+        self.send_json(
+            self.put(&format!("/folder/{id}"))
+                .json(&FolderParams { name }),
+        )
+        .await
+    }
+
+    /// Deletes the folder with all its lists and tasks.
+    pub async fn delete_folder(&self, id: &FolderId) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/folder/{id}"))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_folder_with_lists() {
+        let folder: Folder = serde_json::from_value(serde_json::json!({
+            "id": "115982530",
+            "name": "Roadmap",
+            "orderindex": 0,
+            "override_statuses": false,
+            "hidden": false,
+            "space": { "id": "32279886", "name": "Engineering", "access": true },
+            "task_count": "20",
+            "archived": false,
+            "statuses": [],
+            "lists": [{
+                "id": "188335476",
+                "name": "Milestones",
+                "orderindex": 0,
+                "status": null,
+                "priority": null,
+                "assignee": null,
+                "task_count": 3,
+                "due_date": null,
+                "start_date": null,
+                "space": { "id": "32279886", "name": "Engineering", "access": true },
+                "archived": false,
+                "override_statuses": null,
+                "statuses": []
+            }],
+            "permission_level": "create"
+        }))
+        .unwrap();
+
+        assert_eq!(folder.space.id, SpaceId::from("32279886"));
+        assert_eq!(folder.lists[0].name, "Milestones");
+        assert_eq!(folder.lists[0].folder, None);
+    }
+}
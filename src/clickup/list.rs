@@ -1,5 +1,18 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    folder::{FolderId, FolderRef},
+    space::{SpaceId, SpaceRef},
+    task::{Priority, Status},
+    timestamp,
+    user::{User, UserId},
+};
+
 #[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(transparent)]
 pub struct ListId(pub(crate) String);
@@ -9,3 +22,233 @@ impl From<&str> for ListId {
         Self(id.to_owned())
     }
 }
+
+impl fmt::Display for ListId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The list something is in, as tasks refer to their lists.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListRef {
+    pub id: ListId,
+    pub name: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct List {
+    pub id: ListId,
+    pub name: String,
+    pub orderindex: Option<f64>,
+    /// The description of the list.
+    pub content: Option<String>,
+    /// The status of the list itself, not one of its tasks.
+    pub status: Option<ListStatus>,
+    pub priority: Option<Priority>,
+    pub assignee: Option<User>,
+    #[serde(default, with = "timestamp::option")]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default, with = "timestamp::option")]
+    pub start_date: Option<DateTime<Utc>>,
+    /// Left out for the lists ClickUp nests in their folder.
+    pub folder: Option<FolderRef>,
+    pub space: SpaceRef,
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether the list has its own `statuses` instead of its folder's or space's.
+    pub override_statuses: Option<bool>,
+    #[serde(default)]
+    pub statuses: Vec<Status>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListStatus {
+    pub status: String,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub hide_label: bool,
+}
+
+/// The settings of a list to create.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct NewList {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<UserId>,
+    /// The status of the list itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Changes to a list, `None` leaves a setting as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct ListUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<UserId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Removes the status of the list, overriding `status`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unset_status: bool,
+}
+
+#[derive(Deserialize)]
+struct ListsResponse {
+    lists: Vec<List>,
+}
+
+#[derive(Deserialize)]
+struct MembersResponse {
+    members: Vec<User>,
+}
+
+impl ClickupClient {
+    /// Gets the lists in the folder, the archived ones instead of the others if `archived`.
+    pub async fn get_lists(
+        &self,
+        folder: &FolderId,
+        archived: bool,
+    ) -> Result<Vec<List>, ClickupError> {
+        self.lists(&format!("/folder/{folder}/list"), archived)
+            .await
+    }
+
+    /// Gets the lists directly in the space, outside of any folder.
+    pub async fn get_folderless_lists(
+        &self,
+        space: &SpaceId,
+        archived: bool,
+    ) -> Result<Vec<List>, ClickupError> {
+        self.lists(&format!("/space/{space}/list"), archived).await
+    }
+
+    async fn lists(&self, path: &str, archived: bool) -> Result<Vec<List>, ClickupError> {
+        let response: ListsResponse = self
+            .send_json(self.get(path).query(&[("archived", archived)]))
+            .await?;
+
+        Ok(response.lists)
+    }
+
+    pub async fn get_list(&self, id: &ListId) -> Result<List, ClickupError> {
+        self.send_json(self.get(&format!("/list/{id}"))).await
+    }
+
+    pub async fn create_list(
+        &self,
+        folder: &FolderId,
+        list: &NewList,
+    ) -> Result<List, ClickupError> {
+        self.send_json(self.post(&format!("/folder/{folder}/list")).json(list))
+            .await
+    }
+
+    /// Creates a list directly in the space, outside of any folder.
+    pub async fn create_folderless_list(
+        &self,
+        space: &SpaceId,
+        list: &NewList,
+    ) -> Result<List, ClickupError> {
+        self.send_json(self.post(&format!("/space/{space}/list")).json(list))
+            .await
+    }
+
+    pub async fn update_list(
+        &self,
+        id: &ListId,
+        update: &ListUpdate,
+    ) -> Result<List, ClickupError> {
+        self.send_json(self.put(&format!("/list/{id}")).json(update))
+            .await
+    }
+
+    /// Deletes the list with all tasks whose home it is.
+    pub async fn delete_list(&self, id: &ListId) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/list/{id}"))).await
+    }
+
+    /// Gets the users who can access the list.
+    pub async fn get_list_members(&self, id: &ListId) -> Result<Vec<User>, ClickupError> {
+        let response: MembersResponse = self
+            .send_json(self.get(&format!("/list/{id}/member")))
+            .await?;
+
+        Ok(response.members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_list() {
+        let list: List = serde_json::from_value(serde_json::json!({
+            "id": "188335476",
+            "name": "Milestones",
+            "orderindex": 1,
+            "content": "One task per milestone",
+            "status": { "status": "on track", "color": "#6bc950", "hide_label": true },
+            "priority": { "priority": "high", "color": "#ffcc00" },
+            "assignee": null,
+            "task_count": null,
+            "due_date": "1643007600000",
+            "start_date": null,
+            "folder": { "id": "115982530", "name": "hidden", "hidden": true, "access": true },
+            "space": { "id": "32279886", "name": "Engineering", "access": true },
+            "archived": false,
+            "override_statuses": false,
+            "statuses": [{ "id": "p188335476_abc", "status": "to do", "orderindex": 0, "color": "#d3d3d3", "type": "open" }],
+            "permission_level": "create"
+        }))
+        .unwrap();
+
+        assert_eq!(list.status.unwrap().status, "on track");
+        assert_eq!(list.priority, Some(Priority::High));
+        assert_eq!(
+            list.due_date,
+            Some(Utc.timestamp_millis_opt(1643007600000).unwrap())
+        );
+        assert!(list.folder.unwrap().hidden);
+        assert_eq!(list.space.id, SpaceId::from("32279886"));
+        assert_eq!(list.statuses[0].status, "to do");
+    }
+
+    #[test]
+    fn writes_list_settings() {
+        let list = NewList {
+            name: "Milestones".into(),
+            due_date: Some(Utc.timestamp_millis_opt(1643007600000).unwrap()),
+            priority: Some(Priority::Urgent),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&list).unwrap(),
+            serde_json::json!({ "name": "Milestones", "due_date": 1643007600000i64, "priority": 1 })
+        );
+    }
+}
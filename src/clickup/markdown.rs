@@ -0,0 +1,379 @@
+//! Converts markdown to the rich text of ClickUp comments.
+//!
+//! Inline styles become attributes of the text, while headings, lists, quotes and code blocks
+//! become attributes of the `"\n"` ending each of their lines.
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+
+use super::{
+    comment::{Attributes, Blockquote, CodeBlock, CommentBlock, ListAttribute, ListKind},
+    user::{User, UserId},
+};
+
+/// Converts `markdown` to comment blocks.
+///
+/// `@` followed by a user id, or by the username or email of one of `users`, mentions that user.
+/// Other `@`s are left as they are, as is everything in code.
+pub fn to_comment(markdown: &str, users: &[User]) -> Vec<CommentBlock> {
+    let mut converter = Converter::new(users);
+    for event in Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    ) {
+        converter.event(event);
+    }
+    converter.blocks
+}
+
+struct Converter<'a> {
+    /// Mentionable users, longest names first so `@Karel Doe` is not taken for `@Karel`.
+    users: Vec<(&'a str, UserId)>,
+    blocks: Vec<CommentBlock>,
+    /// The inline style of text.
+    style: Attributes,
+    links: Vec<String>,
+    /// The kind of each list the current line is nested in.
+    lists: Vec<ListKind>,
+    checkbox: Option<bool>,
+    heading: Option<u8>,
+    quotes: usize,
+    code_block: Option<String>,
+    /// Whether text was added since the last line ended.
+    line_open: bool,
+}
+
+impl<'a> Converter<'a> {
+    fn new(users: &'a [User]) -> Self {
+        let mut names: Vec<(&str, UserId)> = users
+            .iter()
+            .flat_map(|user| {
+                [user.username.as_deref(), user.email.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .map(move |name| (name, user.id))
+            })
+            .collect();
+        names.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+        Self {
+            users: names,
+            blocks: Vec::new(),
+            style: Attributes::default(),
+            links: Vec::new(),
+            lists: Vec::new(),
+            checkbox: None,
+            heading: None,
+            quotes: 0,
+            code_block: None,
+            line_open: false,
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.code_block.is_some() => {
+                for (i, line) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        self.end_line();
+                    }
+                    self.push_text(line, Attributes::default());
+                }
+            }
+            Event::Text(text) => self.push_mentions(&text),
+            Event::Code(code) => {
+                let attributes = Attributes {
+                    code: true,
+                    ..self.inline_style()
+                };
+                self.push_text(&code, attributes);
+            }
+            Event::Html(html) => self.push_text(&html, self.inline_style()),
+            Event::SoftBreak => self.push_text(" ", self.inline_style()),
+            Event::HardBreak => self.end_line(),
+            Event::Rule => {
+                self.push_text("---", Attributes::default());
+                self.end_line();
+            }
+            Event::TaskListMarker(checked) => self.checkbox = Some(checked),
+            Event::FootnoteReference(name) => {
+                self.push_text(&format!("[^{name}]"), self.inline_style())
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading(level, ..) => self.heading = Some(heading_level(level)),
+            Tag::BlockQuote => self.quotes += 1,
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(str::to_owned)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                self.code_block = Some(language.unwrap_or_else(|| "plain".to_owned()));
+            }
+            Tag::List(start) => {
+                // The text of the item this list is nested in is a line of its own.
+                if self.line_open {
+                    self.end_line();
+                }
+                self.lists.push(match start {
+                    Some(_) => ListKind::Ordered,
+                    None => ListKind::Bullet,
+                });
+            }
+            Tag::Item => self.checkbox = None,
+            Tag::Emphasis => self.style.italic = true,
+            Tag::Strong => self.style.bold = true,
+            Tag::Strikethrough => self.style.strike = true,
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => self.links.push(url.to_string()),
+            Tag::Paragraph | Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead => {}
+            Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::Item => {
+                // Code blocks end with a newline of their own, and items may have ended their
+                // line with a paragraph.
+                if self.line_open || !matches!(tag, Tag::Item | Tag::CodeBlock(_)) {
+                    self.end_line();
+                }
+                match tag {
+                    Tag::Heading(..) => self.heading = None,
+                    Tag::CodeBlock(_) => self.code_block = None,
+                    _ => {}
+                }
+            }
+            Tag::BlockQuote => self.quotes -= 1,
+            Tag::List(_) => {
+                self.lists.pop();
+            }
+            Tag::Emphasis => self.style.italic = false,
+            Tag::Strong => self.style.bold = false,
+            Tag::Strikethrough => self.style.strike = false,
+            Tag::Link(..) | Tag::Image(..) => {
+                self.links.pop();
+            }
+            Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead => {}
+            Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn inline_style(&self) -> Attributes {
+        Attributes {
+            link: self.links.last().cloned(),
+            ..self.style.clone()
+        }
+    }
+
+    /// The attributes of the `"\n"` ending the current line.
+    fn line_style(&self) -> Attributes {
+        let list = self.lists.last().map(|&kind| ListAttribute {
+            list: match self.checkbox {
+                Some(true) => ListKind::Checked,
+                Some(false) => ListKind::Unchecked,
+                None => kind,
+            },
+        });
+
+        Attributes {
+            header: self.heading,
+            list,
+            indent: match self.lists.len() {
+                0 | 1 => None,
+                depth => Some((depth - 1) as u8),
+            },
+            blockquote: (self.quotes > 0).then_some(Blockquote {}),
+            code_block: self
+                .code_block
+                .clone()
+                .map(|language| CodeBlock { language }),
+            ..Default::default()
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.blocks.push(CommentBlock::Text {
+            text: "\n".to_owned(),
+            attributes: self.line_style(),
+        });
+        self.line_open = false;
+    }
+
+    /// Adds text, joining it with the previous text if that has the same style.
+    fn push_text(&mut self, text: &str, attributes: Attributes) {
+        if text.is_empty() {
+            return;
+        }
+        self.line_open = true;
+
+        if let Some(CommentBlock::Text {
+            text: last,
+            attributes: last_attributes,
+        }) = self.blocks.last_mut()
+        {
+            if *last_attributes == attributes && !last.ends_with('\n') {
+                last.push_str(text);
+                return;
+            }
+        }
+        self.blocks.push(CommentBlock::Text {
+            text: text.to_owned(),
+            attributes,
+        });
+    }
+
+    /// Adds text, turning `@`s of users into mentions.
+    fn push_mentions(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(at) = find_mention_start(rest) {
+            let (before, candidate) = rest.split_at(at);
+            match self.mention(&candidate[1..]) {
+                Some((user, len)) => {
+                    self.push_text(before, self.inline_style());
+                    self.blocks.push(CommentBlock::mention(user));
+                    self.line_open = true;
+                    rest = &candidate[1 + len..];
+                }
+                None => {
+                    self.push_text(&rest[..at + 1], self.inline_style());
+                    rest = &candidate[1..];
+                }
+            }
+        }
+        self.push_text(rest, self.inline_style());
+    }
+
+    /// The user mentioned at the start of `text` and the length of their name or id.
+    fn mention(&self, text: &str) -> Option<(UserId, usize)> {
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 && ends_word(&text[digits..]) {
+            return Some((UserId(text[..digits].parse().ok()?), digits));
+        }
+
+        self.users.iter().find_map(|&(name, user)| {
+            let prefix = text.get(..name.len())?;
+            (prefix.eq_ignore_ascii_case(name) && ends_word(&text[name.len()..]))
+                .then_some((user, name.len()))
+        })
+    }
+}
+
+/// The position of the next `@` that starts a word.
+fn find_mention_start(text: &str) -> Option<usize> {
+    text.char_indices()
+        .find(|&(i, c)| {
+            c == '@'
+                && text[..i]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|c| !c.is_alphanumeric())
+        })
+        .map(|(i, _)| i)
+}
+
+fn ends_word(rest: &str) -> bool {
+    rest.chars()
+        .next()
+        .is_none_or(|c| !c.is_alphanumeric() && c != '_')
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn convert(markdown: &str) -> serde_json::Value {
+        let users = [User {
+            id: UserId(38221385),
+            username: Some("Karel Doe".into()),
+            email: Some("karel@example.com".into()),
+            ..Default::default()
+        }];
+        serde_json::to_value(to_comment(markdown, &users)).unwrap()
+    }
+
+    #[test]
+    fn converts_inline_styles() {
+        assert_eq!(
+            convert("Moved under **v2** because `Milestone` [changed](https://app.clickup.com/t/36w7wbr)"),
+            json!([
+                { "text": "Moved under " },
+                { "text": "v2", "attributes": { "bold": true } },
+                { "text": " because " },
+                { "text": "Milestone", "attributes": { "code": true } },
+                { "text": " " },
+                { "text": "changed", "attributes": { "link": "https://app.clickup.com/t/36w7wbr" } },
+                { "text": "\n" }
+            ])
+        );
+    }
+
+    #[test]
+    fn converts_block_styles() {
+        assert_eq!(
+            convert("# Moves\n\n- one\n  - nested\n- [x] done\n\n> quoted\n\n```rust\nlet a;\nlet b;\n```"),
+            json!([
+                { "text": "Moves" },
+                { "text": "\n", "attributes": { "header": 1 } },
+                { "text": "one" },
+                { "text": "\n", "attributes": { "list": { "list": "bullet" } } },
+                { "text": "nested" },
+                { "text": "\n", "attributes": { "list": { "list": "bullet" }, "indent": 1 } },
+                { "text": "done" },
+                { "text": "\n", "attributes": { "list": { "list": "checked" } } },
+                { "text": "quoted" },
+                { "text": "\n", "attributes": { "blockquote": {} } },
+                { "text": "let a;" },
+                { "text": "\n", "attributes": { "code-block": { "code-block": "rust" } } },
+                { "text": "let b;" },
+                { "text": "\n", "attributes": { "code-block": { "code-block": "rust" } } }
+            ])
+        );
+    }
+
+    #[test]
+    fn mentions_users_by_name_email_or_id() {
+        assert_eq!(
+            convert("@karel doe, @karel@example.com and @38221385 but not @someone or me@home"),
+            json!([
+                { "type": "tag", "user": { "id": 38221385 } },
+                { "text": ", " },
+                { "type": "tag", "user": { "id": 38221385 } },
+                { "text": " and " },
+                { "type": "tag", "user": { "id": 38221385 } },
+                { "text": " but not @someone or me@home" },
+                { "text": "\n" }
+            ])
+        );
+    }
+
+    #[test]
+    fn keeps_mentions_in_code() {
+        assert_eq!(
+            convert("`@38221385`"),
+            json!([
+                { "text": "@38221385", "attributes": { "code": true } },
+                { "text": "\n" }
+            ])
+        );
+    }
+}
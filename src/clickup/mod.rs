@@ -1,6 +1,24 @@
 pub mod actions;
 pub mod auth;
+pub mod cache;
+pub mod checklist;
+pub mod client;
+pub mod comment;
+pub mod custom_field;
+pub mod dependency;
+pub mod duration;
+pub mod error;
+pub mod folder;
 pub mod list;
+pub mod markdown;
+pub mod search;
+pub mod space;
+pub mod string_or_number;
+pub mod tag;
 pub mod task;
 pub mod team;
+pub mod time;
+pub mod timestamp;
+pub mod user;
 pub mod webhooks;
+pub mod writes;
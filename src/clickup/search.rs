@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    client::ClickupClient,
+    error::ClickupError,
+    list::ListId,
+    task::{Task, TaskId},
+    team::TeamId,
+    user::UserId,
+};
+
+/// ClickUp returns at most this many tasks per page, a shorter page is the last one.
+const PAGE_SIZE: usize = 100;
+
+/// Id of a view, such as a board or a table, whose tasks can be listed like those of a list.
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct ViewId(pub(crate) String);
+
+impl From<&str> for ViewId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+/// How ClickUp sorts the tasks it returns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OrderBy {
+    Id,
+    Created,
+    Updated,
+    DueDate,
+}
+
+impl OrderBy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::DueDate => "due_date",
+        }
+    }
+}
+
+/// Compares the value of a custom field, such as `{"field_id": ..., "operator": "=", "value":
+/// "done"}`. ClickUp supports `=`, `<`, `<=`, `>`, `>=`, `!=`, `IS NULL`, `IS NOT NULL`, `RANGE`,
+/// `ANY`, `ALL` and `NOT ANY`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CustomFieldFilter {
+    pub field_id: String,
+    pub operator: String,
+    pub value: Value,
+}
+
+impl CustomFieldFilter {
+    pub fn new(field_id: &str, operator: &str, value: impl Into<Value>) -> Self {
+        Self {
+            field_id: field_id.to_owned(),
+            operator: operator.to_owned(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Which tasks to get from a list or team, everything left empty does not filter.
+///
+/// Date ranges are exclusive on both ends, as ClickUp only offers greater and less than.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskFilter {
+    pub archived: bool,
+    pub order_by: Option<OrderBy>,
+    pub reverse: bool,
+    /// Also return subtasks, not only top level tasks.
+    pub subtasks: bool,
+    /// Names of the statuses to return tasks in.
+    pub statuses: Vec<String>,
+    /// Also return closed tasks, which ClickUp leaves out by default.
+    pub include_closed: bool,
+    pub assignees: Vec<UserId>,
+    /// Names of tags, a task needs any of them.
+    pub tags: Vec<String>,
+    pub custom_fields: Vec<CustomFieldFilter>,
+    pub due_date_gt: Option<DateTime<Utc>>,
+    pub due_date_lt: Option<DateTime<Utc>>,
+    pub date_created_gt: Option<DateTime<Utc>>,
+    pub date_created_lt: Option<DateTime<Utc>>,
+    pub date_updated_gt: Option<DateTime<Utc>>,
+    pub date_updated_lt: Option<DateTime<Utc>>,
+    /// Only for team tasks, the spaces, folders and lists to search in.
+    pub space_ids: Vec<String>,
+    pub folder_ids: Vec<String>,
+    pub list_ids: Vec<ListId>,
+    /// Only return the direct subtasks of this task, which needs `subtasks` set.
+    pub parent: Option<TaskId>,
+}
+
+impl TaskFilter {
+    /// The filter as query parameters, array parameters repeated as `statuses[]=a&statuses[]=b`.
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+
+        let flags = [
+            ("archived", self.archived),
+            ("reverse", self.reverse),
+            ("subtasks", self.subtasks),
+            ("include_closed", self.include_closed),
+        ];
+        for (name, set) in flags {
+            if set {
+                query.push((name, "true".to_owned()));
+            }
+        }
+        if let Some(order_by) = self.order_by {
+            query.push(("order_by", order_by.as_str().to_owned()));
+        }
+
+        query.extend(self.statuses.iter().map(|s| ("statuses[]", s.clone())));
+        query.extend(
+            self.assignees
+                .iter()
+                .map(|a| ("assignees[]", a.to_string())),
+        );
+        query.extend(self.tags.iter().map(|t| ("tags[]", t.clone())));
+        query.extend(self.space_ids.iter().map(|s| ("space_ids[]", s.clone())));
+        query.extend(self.folder_ids.iter().map(|f| ("project_ids[]", f.clone())));
+        query.extend(self.list_ids.iter().map(|l| ("list_ids[]", l.0.clone())));
+        query.extend(self.parent.iter().map(|p| ("parent", p.0.clone())));
+
+        if !self.custom_fields.is_empty() {
+            query.push(("custom_fields", json!(self.custom_fields).to_string()));
+        }
+
+        let dates = [
+            ("due_date_gt", self.due_date_gt),
+            ("due_date_lt", self.due_date_lt),
+            ("date_created_gt", self.date_created_gt),
+            ("date_created_lt", self.date_created_lt),
+            ("date_updated_gt", self.date_updated_gt),
+            ("date_updated_lt", self.date_updated_lt),
+        ];
+        for (name, date) in dates {
+            if let Some(date) = date {
+                query.push((name, date.timestamp_millis().to_string()));
+            }
+        }
+
+        query
+    }
+}
+
+#[derive(Deserialize)]
+struct TasksResponse {
+    tasks: Vec<Task>,
+    /// Only sent by some endpoints, the others end with a short page.
+    last_page: Option<bool>,
+}
+
+impl TasksResponse {
+    fn is_last_page(&self) -> bool {
+        self.last_page.unwrap_or(self.tasks.len() < PAGE_SIZE)
+    }
+}
+
+impl ClickupClient {
+    /// Gets the tasks in `list` matching `filter`, fetching further pages as the stream is read.
+    ///
+    /// Only tasks whose home is the list are returned, not those added to it from elsewhere.
+    pub fn get_tasks(
+        &self,
+        list: &ListId,
+        filter: &TaskFilter,
+    ) -> impl Stream<Item = Result<Task, ClickupError>> + Send + 'static {
+        self.paginate(format!("/list/{}/task", list.0), filter.query())
+    }
+
+    /// Gets the tasks anywhere in the team matching `filter`, narrowed down to its spaces,
+    /// folders and lists if set.
+    pub fn get_filtered_team_tasks(
+        &self,
+        team: TeamId,
+        filter: &TaskFilter,
+    ) -> impl Stream<Item = Result<Task, ClickupError>> + Send + 'static {
+        self.paginate(format!("/team/{team}/task"), filter.query())
+    }
+
+    /// Gets the tasks shown in a view, filtered and sorted the way the view is.
+    pub fn get_view_tasks(
+        &self,
+        view: &ViewId,
+    ) -> impl Stream<Item = Result<Task, ClickupError>> + Send + 'static {
+        self.paginate(format!("/view/{}/task", view.0), Vec::new())
+    }
+
+    /// Requests pages of tasks from `path` until the last one. The tasks are put in the task
+    /// cache on the way, and an error ends the stream.
+    fn paginate(
+        &self,
+        path: String,
+        query: Vec<(&'static str, String)>,
+    ) -> impl Stream<Item = Result<Task, ClickupError>> + Send + 'static {
+        let client = self.clone();
+
+        stream::try_unfold(Some(0u32), move |page| {
+            let client = client.clone();
+            let request = client.get(&path).query(&query);
+            async move {
+                let Some(page) = page else {
+                    return Ok::<_, ClickupError>(None);
+                };
+
+                let cache = client.connection().tasks();
+                let generation = cache.generation();
+                let response: TasksResponse =
+                    client.send_json(request.query(&[("page", page)])).await?;
+                let next = (!response.is_last_page()).then_some(page + 1);
+
+                for task in &response.tasks {
+                    cache.insert(client.token(), task.clone(), generation);
+                }
+
+                Ok(Some((
+                    stream::iter(response.tasks.into_iter().map(Ok)),
+                    next,
+                )))
+            }
+        })
+        .try_flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn builds_filter_query() {
+        let filter = TaskFilter {
+            subtasks: true,
+            include_closed: true,
+            order_by: Some(OrderBy::Updated),
+            statuses: vec!["in progress".into(), "review".into()],
+            assignees: vec![UserId(38221385)],
+            custom_fields: vec![CustomFieldFilter::new(
+                "de761538-8ae0-42e8-91d9-f1a0cdfbd8b5",
+                "=",
+                "b2",
+            )],
+            date_updated_gt: Some(Utc.timestamp_millis_opt(1643007600000).unwrap()),
+            list_ids: vec![ListId::from("180471464")],
+            parent: Some(TaskId::from("36pnwzu")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter.query(),
+            vec![
+                ("subtasks", "true".to_owned()),
+                ("include_closed", "true".to_owned()),
+                ("order_by", "updated".to_owned()),
+                ("statuses[]", "in progress".to_owned()),
+                ("statuses[]", "review".to_owned()),
+                ("assignees[]", "38221385".to_owned()),
+                ("list_ids[]", "180471464".to_owned()),
+                ("parent", "36pnwzu".to_owned()),
+                (
+                    "custom_fields",
+                    r#"[{"field_id":"de761538-8ae0-42e8-91d9-f1a0cdfbd8b5","operator":"=","value":"b2"}]"#
+                        .to_owned()
+                ),
+                ("date_updated_gt", "1643007600000".to_owned()),
+            ]
+        );
+        assert!(TaskFilter::default().query().is_empty());
+    }
+
+    #[test]
+    fn detects_last_page() {
+        let page = |tasks: usize, last_page: Option<bool>| TasksResponse {
+            tasks: vec![Task::default(); tasks],
+            last_page,
+        };
+
+        assert!(page(3, None).is_last_page());
+        assert!(!page(PAGE_SIZE, None).is_last_page());
+        assert!(page(PAGE_SIZE, Some(true)).is_last_page());
+        assert!(!page(3, Some(false)).is_last_page());
+    }
+}
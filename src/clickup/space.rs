@@ -0,0 +1,219 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{client::ClickupClient, error::ClickupError, task::Status, team::TeamId, user::User};
+
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct SpaceId(pub(crate) String);
+
+impl From<&str> for SpaceId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for SpaceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The space something is in, as tasks, folders and lists refer to it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpaceRef {
+    pub id: SpaceId,
+    pub name: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Space {
+    pub id: SpaceId,
+    pub name: String,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub private: bool,
+    /// The statuses of the space, used by its folders and lists unless they override them.
+    #[serde(default)]
+    pub statuses: Vec<Status>,
+    #[serde(default)]
+    pub multiple_assignees: bool,
+    #[serde(default)]
+    pub features: SpaceFeatures,
+    #[serde(default)]
+    pub members: Vec<Member>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub user: User,
+}
+
+/// The ClickApps enabled in a space, `None` for those ClickUp did not report.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpaceFeatures {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_dates: Option<DueDatesFeature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_tracking: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_estimates: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checklists: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_fields: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remap_dependencies: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependency_warning: Option<Feature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub portfolios: Option<Feature>,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feature {
+    pub enabled: bool,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DueDatesFeature {
+    pub enabled: bool,
+    #[serde(default)]
+    pub start_date: bool,
+    /// Move the due dates of subtasks along with their parent's.
+    #[serde(default)]
+    pub remap_due_dates: bool,
+    /// Also move the due dates of closed subtasks.
+    #[serde(default)]
+    pub remap_closed_due_date: bool,
+}
+
+/// The settings of a space to create.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct NewSpace {
+    pub name: String,
+    pub multiple_assignees: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<SpaceFeatures>,
+}
+
+/// Changes to a space, `None` leaves a setting as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct SpaceUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multiple_assignees: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<SpaceFeatures>,
+}
+
+#[derive(Deserialize)]
+struct SpacesResponse {
+    spaces: Vec<Space>,
+}
+
+impl ClickupClient {
+    /// Gets the spaces of the team, the archived ones instead of the others if `archived`.
+    pub async fn get_spaces(
+        &self,
+        team: TeamId,
+        archived: bool,
+    ) -> Result<Vec<Space>, ClickupError> {
+        let response: SpacesResponse = self
+            .send_json(
+                self.get(&format!("/team/{team}/space"))
+                    .query(&[("archived", archived)]),
+            )
+            .await?;
+
+        Ok(response.spaces)
+    }
+
+    pub async fn get_space(&self, id: &SpaceId) -> Result<Space, ClickupError> {
+        self.send_json(self.get(&format!("/space/{id}"))).await
+    }
+
+    pub async fn create_space(
+        &self,
+        team: TeamId,
+        space: &NewSpace,
+    ) -> Result<Space, ClickupError> {
+        self.send_json(self.post(&format!("/team/{team}/space")).json(space))
+            .await
+    }
+
+    pub async fn update_space(
+        &self,
+        id: &SpaceId,
+        update: &SpaceUpdate,
+    ) -> Result<Space, ClickupError> {
+        self.send_json(self.put(&format!("/space/{id}")).json(update))
+            .await
+    }
+
+    /// Deletes the space with all its folders, lists and tasks.
+    pub async fn delete_space(&self, id: &SpaceId) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/space/{id}"))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clickup::task::StatusType;
+
+    #[test]
+    fn parses_space() {
+        let space: Space = serde_json::from_value(serde_json::json!({
+            "id": "32279886",
+            "name": "Engineering",
+            "private": false,
+            "statuses": [
+                { "id": "p32279886_abc", "status": "to do", "type": "open", "orderindex": 0, "color": "#d3d3d3" },
+                { "id": "p32279886_def", "status": "complete", "type": "closed", "orderindex": 1, "color": "#6bc950" }
+            ],
+            "multiple_assignees": true,
+            "features": {
+                "due_dates": { "enabled": true, "start_date": true, "remap_due_dates": false, "remap_closed_due_date": false },
+                "time_tracking": { "enabled": false },
+                "tags": { "enabled": true },
+                "checklists": { "enabled": true }
+            },
+            "members": [{ "user": { "id": 38221385, "username": "Karel", "color": "#7b68ee", "profilePicture": null, "initials": "K" } }],
+            "archived": false
+        }))
+        .unwrap();
+
+        assert_eq!(space.statuses[1].kind, StatusType::Closed);
+        assert!(space.features.due_dates.unwrap().start_date);
+        assert_eq!(
+            space.features.time_tracking,
+            Some(Feature { enabled: false })
+        );
+        assert_eq!(space.features.portfolios, None);
+        assert_eq!(space.members[0].user.id, 38221385.into());
+    }
+
+    #[test]
+    fn update_skips_unset_settings() {
+        let update = SpaceUpdate {
+            name: Some("Product".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({ "name": "Product" })
+        );
+    }
+}
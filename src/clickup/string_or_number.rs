@@ -0,0 +1,130 @@
+//! Deserializes values ClickUp sends as numbers in some places and as strings in others, such as
+//! ids and timestamps, for `#[serde(deserialize_with = "...")]`.
+//!
+//! Either is parsed with the type's `FromStr`.
+
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use serde::{
+    de::{self, Unexpected},
+    Deserializer,
+};
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    deserializer
+        .deserialize_any(Visitor::<T>::new(false))?
+        .ok_or_else(|| de::Error::custom("missing value"))
+}
+
+/// For optional values, which ClickUp sends as `null` or as an empty string when unset.
+pub mod option {
+    use super::*;
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        deserializer.deserialize_any(Visitor::<T>::new(true))
+    }
+}
+
+struct Visitor<T> {
+    optional: bool,
+    value: PhantomData<T>,
+}
+
+impl<T> Visitor<T> {
+    fn new(optional: bool) -> Self {
+        Self {
+            optional,
+            value: PhantomData,
+        }
+    }
+
+    fn parse<E: de::Error>(&self, value: &str) -> Result<Option<T>, E>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        value.parse().map(Some).map_err(E::custom)
+    }
+}
+
+impl<'de, T> de::Visitor<'de> for Visitor<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number or a string")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        self.parse(&value.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        self.parse(&value.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        if self.optional && value.is_empty() {
+            return Ok(None);
+        }
+        self.parse(value)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        if self.optional {
+            Ok(None)
+        } else {
+            Err(E::invalid_type(Unexpected::Unit, &self))
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Ids {
+        #[serde(deserialize_with = "super::deserialize")]
+        id: u64,
+        #[serde(default, deserialize_with = "super::option::deserialize")]
+        parent: Option<String>,
+    }
+
+    #[test]
+    fn reads_numbers_and_strings() {
+        let ids: Ids = serde_json::from_str(r#"{"id":"20131398","parent":94}"#).unwrap();
+        assert_eq!(
+            ids,
+            Ids {
+                id: 20131398,
+                parent: Some("94".into())
+            }
+        );
+
+        let ids: Ids = serde_json::from_str(r#"{"id":20131398,"parent":""}"#).unwrap();
+        assert_eq!(ids.parent, None);
+        let ids: Ids = serde_json::from_str(r#"{"id":20131398,"parent":null}"#).unwrap();
+        assert_eq!(ids.parent, None);
+
+        assert!(serde_json::from_str::<Ids>(r#"{"id":"abc"}"#).is_err());
+        assert!(serde_json::from_str::<Ids>(r#"{"id":null}"#).is_err());
+    }
+}
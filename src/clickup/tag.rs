@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::{path_segment, ClickupClient},
+    error::ClickupError,
+    space::SpaceId,
+    task::TaskId,
+    user::UserId,
+};
+
+/// A tag of a space, which tasks in the space can carry. Tags are identified by their name.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    /// Text color of the tag.
+    pub tag_fg: Option<String>,
+    /// Background color of the tag.
+    pub tag_bg: Option<String>,
+    pub creator: Option<UserId>,
+}
+
+#[derive(Serialize)]
+struct CreateTagParams<'a> {
+    tag: NewTag<'a>,
+}
+
+#[derive(Serialize)]
+struct NewTag<'a> {
+    name: &'a str,
+    tag_fg: &'a str,
+    tag_bg: &'a str,
+}
+
+/// Editing a tag names its colors differently than creating one.
+#[derive(Serialize)]
+struct EditTagParams<'a> {
+    tag: TagEdit<'a>,
+}
+
+#[derive(Serialize)]
+struct TagEdit<'a> {
+    name: &'a str,
+    fg_color: &'a str,
+    bg_color: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    tags: Vec<Tag>,
+}
+
+impl ClickupClient {
+    pub async fn get_space_tags(&self, space: &SpaceId) -> Result<Vec<Tag>, ClickupError> {
+        let response: TagsResponse = self
+            .send_json(self.get(&format!("/space/{space}/tag")))
+            .await?;
+
+        Ok(response.tags)
+    }
+
+    /// Creates a tag in the space with the given text and background colors, such as `#800000`.
+    pub async fn create_space_tag(
+        &self,
+        space: &SpaceId,
+        name: &str,
+        fg: &str,
+        bg: &str,
+    ) -> Result<(), ClickupError> {
+        let params = CreateTagParams {
+            tag: NewTag {
+                name,
+                tag_fg: fg,
+                tag_bg: bg,
+            },
+        };
+
+        self.send(self.post(&format!("/space/{space}/tag")).json(&params))
+            .await
+    }
+
+    /// Renames and recolors the tag `name`, on all tasks that carry it.
+    pub async fn edit_space_tag(
+        &self,
+        space: &SpaceId,
+        name: &str,
+        new_name: &str,
+        fg: &str,
+        bg: &str,
+    ) -> Result<(), ClickupError> {
+        let params = EditTagParams {
+            tag: TagEdit {
+                name: new_name,
+                fg_color: fg,
+                bg_color: bg,
+            },
+        };
+
+        self.send(
+            self.put(&format!("/space/{space}/tag/{}", path_segment(name)))
+                .json(&params),
+        )
+        .await
+    }
+
+    /// Deletes the tag from the space and all tasks that carry it.
+    pub async fn delete_space_tag(&self, space: &SpaceId, name: &str) -> Result<(), ClickupError> {
+        self.send(self.delete(&format!("/space/{space}/tag/{}", path_segment(name))))
+            .await
+    }
+
+    /// Adds a tag of its space to the task.
+    pub async fn add_tag_to_task(&self, task: &TaskId, name: &str) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.post(&format!("/task/{}/tag/{}", task.0, path_segment(name))))
+            .await;
+        self.connection().tasks().invalidate(task);
+        if result.is_ok() {
+            self.connection().own_writes().record(task, "tag");
+        }
+        result
+    }
+
+    pub async fn remove_tag_from_task(
+        &self,
+        task: &TaskId,
+        name: &str,
+    ) -> Result<(), ClickupError> {
+        let result = self
+            .send(self.delete(&format!("/task/{}/tag/{}", task.0, path_segment(name))))
+            .await;
+        self.connection().tasks().invalidate(task);
+        if result.is_ok() {
+            self.connection().own_writes().record(task, "tag_removed");
+        }
+        result
+    }
+}
@@ -1,7 +1,23 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use std::fmt;
 
-use super::list::ListId;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    checklist::Checklist,
+    custom_field::CustomField,
+    dependency::{Dependency, LinkedTask},
+    duration,
+    folder::FolderRef,
+    list::ListRef,
+    space::SpaceRef,
+    string_or_number,
+    tag::Tag,
+    team::TeamId,
+    timestamp,
+    user::User,
+    user::UserId,
+};
 
 #[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(transparent)]
@@ -16,40 +32,545 @@ impl From<&str> for TaskId {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub id: TaskId,
+    /// The workspace's own id for the task, when custom task ids are enabled.
+    pub custom_id: Option<String>,
     pub name: Option<String>,
+    /// The description as plain text.
+    pub text_content: Option<String>,
     pub description: Option<String>,
     pub status: Option<Status>,
+    pub url: Option<String>,
+    pub team_id: Option<TeamId>,
+    pub creator: Option<User>,
+    #[serde(default)]
+    pub assignees: Vec<User>,
+    #[serde(default)]
+    pub watchers: Vec<User>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    pub priority: Option<Priority>,
+    #[serde(default, with = "timestamp::option")]
+    pub date_created: Option<DateTime<Utc>>,
+    #[serde(default, with = "timestamp::option")]
+    pub date_updated: Option<DateTime<Utc>>,
+    #[serde(default, with = "timestamp::option")]
+    pub date_closed: Option<DateTime<Utc>>,
+    #[serde(default, with = "timestamp::option")]
+    pub start_date: Option<DateTime<Utc>>,
+    #[serde(default, with = "timestamp::option")]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default, with = "duration::option")]
+    pub time_estimate: Option<Duration>,
+    #[serde(default, with = "duration::option")]
+    pub time_spent: Option<Duration>,
+    pub points: Option<f64>,
+    #[serde(default)]
+    pub archived: bool,
     pub parent: Option<TaskId>,
+    #[serde(default)]
+    pub checklists: Vec<Checklist>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub linked_tasks: Vec<LinkedTask>,
     pub custom_fields: Vec<CustomField>,
-    pub list: List,
-    pub folder: Folder,
-    pub space: Space,
+    pub list: ListRef,
+    pub folder: FolderRef,
+    pub space: SpaceRef,
+    /// The lists the task was added to besides its home `list`.
+    #[serde(default)]
+    pub locations: Vec<ListRef>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct CustomField {
-    pub id: String,
-    pub name: String,
-    pub r#type: String,
-    pub value: Option<Value>,
+impl Task {
+    /// The custom field with the given id, or else name.
+    pub fn custom_field(&self, field: &str) -> Option<&CustomField> {
+        self.custom_fields
+            .iter()
+            .find(|f| f.id == field)
+            .or_else(|| self.custom_fields.iter().find(|f| f.name == field))
+    }
+}
+
+/// A task to create, built up from its name.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct NewTask {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    markdown_content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    assignees: Vec<UserId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    due_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_date_time: Option<bool>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    start_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_time: Option<bool>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "duration::option::serialize"
+    )]
+    time_estimate: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<TaskId>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    notify_all: bool,
+}
+
+impl NewTask {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The description as plain text.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The description as markdown, which ClickUp prefers over a plain `description`.
+    pub fn markdown(mut self, markdown: impl Into<String>) -> Self {
+        self.markdown_content = Some(markdown.into());
+        self
+    }
+
+    pub fn assignee(mut self, user: UserId) -> Self {
+        self.assignees.push(user);
+        self
+    }
+
+    /// Adds a tag by name, creating it in the space if it does not exist.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// The status by name, the first status of the list when not set.
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// The due date, including its time of day.
+    pub fn due_date(mut self, date: DateTime<Utc>) -> Self {
+        self.due_date = Some(date);
+        self.due_date_time = Some(true);
+        self
+    }
+
+    /// The start date, including its time of day.
+    pub fn start_date(mut self, date: DateTime<Utc>) -> Self {
+        self.start_date = Some(date);
+        self.start_date_time = Some(true);
+        self
+    }
+
+    pub fn time_estimate(mut self, estimate: Duration) -> Self {
+        self.time_estimate = Some(estimate);
+        self
+    }
+
+    /// Creates the task as a subtask of `parent`, which must be in the same list.
+    pub fn parent(mut self, parent: TaskId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Notifies the creator as well as the assignees and watchers.
+    pub fn notify_all(mut self) -> Self {
+        self.notify_all = true;
+        self
+    }
+}
+
+/// Changes to a task, everything not set is left as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct TaskUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    markdown_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<Option<Priority>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::clearable::serialize"
+    )]
+    due_date: Option<Option<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_date_time: Option<bool>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::clearable::serialize"
+    )]
+    start_date: Option<Option<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_time: Option<bool>,
+    #[serde(skip_serializing_if = "AssigneesUpdate::is_empty")]
+    assignees: AssigneesUpdate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<TaskId>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+struct AssigneesUpdate {
+    add: Vec<UserId>,
+    rem: Vec<UserId>,
+}
+
+impl AssigneesUpdate {
+    fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.rem.is_empty()
+    }
+}
+
+impl TaskUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Replaces the description with plain text.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Replaces the description with markdown, which ClickUp prefers over a plain `description`.
+    pub fn markdown(mut self, markdown: impl Into<String>) -> Self {
+        self.markdown_content = Some(markdown.into());
+        self
+    }
+
+    /// Moves the task to the status with this name.
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    /// Sets the priority, or removes it with `None`.
+    pub fn priority(mut self, priority: Option<Priority>) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Sets the due date including its time of day, or removes it with `None`.
+    pub fn due_date(mut self, date: Option<DateTime<Utc>>) -> Self {
+        self.due_date_time = date.map(|_| true);
+        self.due_date = Some(date);
+        self
+    }
+
+    /// Sets the start date including its time of day, or removes it with `None`.
+    pub fn start_date(mut self, date: Option<DateTime<Utc>>) -> Self {
+        self.start_date_time = date.map(|_| true);
+        self.start_date = Some(date);
+        self
+    }
+
+    pub fn add_assignee(mut self, user: UserId) -> Self {
+        self.assignees.add.push(user);
+        self
+    }
+
+    pub fn remove_assignee(mut self, user: UserId) -> Self {
+        self.assignees.rem.push(user);
+        self
+    }
+
+    pub fn archived(mut self, archived: bool) -> Self {
+        self.archived = Some(archived);
+        self
+    }
+
+    /// Makes the task a subtask of `parent`, which moves it to the parent's list.
+    pub fn parent(mut self, parent: TaskId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// The `field`s of the history items ClickUp reports the update with, one per change.
+    pub(crate) fn history_fields(&self) -> Vec<&'static str> {
+        let changes = [
+            ("name", self.name.is_some()),
+            (
+                "content",
+                self.description.is_some() || self.markdown_content.is_some(),
+            ),
+            ("status", self.status.is_some()),
+            ("priority", self.priority.is_some()),
+            ("due_date", self.due_date.is_some()),
+            ("start_date", self.start_date.is_some()),
+            ("archived", self.archived.is_some()),
+            ("parent", self.parent.is_some()),
+        ];
+
+        let mut fields: Vec<_> = changes
+            .into_iter()
+            .filter_map(|(field, changed)| changed.then_some(field))
+            .collect();
+        fields.extend(self.assignees.add.iter().map(|_| "assignee_add"));
+        fields.extend(self.assignees.rem.iter().map(|_| "assignee_rem"));
+        fields
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Status {
     pub status: String,
+    pub color: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: StatusType,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct List {
-    pub id: ListId,
+/// Where a status sits in the workflow of a list.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusType {
+    /// The first status, for tasks that were not started.
+    Open,
+    #[default]
+    Custom,
+    /// Tasks in it count as done but are not closed yet.
+    Done,
+    Closed,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Folder {
-    pub id: String,
+/// ClickUp's fixed priorities, sent as their number when writing tasks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    Urgent = 1,
+    High = 2,
+    Normal = 3,
+    Low = 4,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Space {
-    pub id: String,
+impl Priority {
+    pub fn from_number(n: u64) -> Option<Self> {
+        match n {
+            1 => Some(Self::Urgent),
+            2 => Some(Self::High),
+            3 => Some(Self::Normal),
+            4 => Some(Self::Low),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "urgent" => Some(Self::Urgent),
+            "high" => Some(Self::High),
+            "normal" => Some(Self::Normal),
+            "low" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Urgent => "urgent",
+            Self::High => "high",
+            Self::Normal => "normal",
+            Self::Low => "low",
+        })
+    }
+}
+
+impl Serialize for Priority {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// Tasks carry their priority as `{"id": "1", "priority": "urgent", ...}` and lists as
+/// `{"priority": "urgent", ...}`, while webhook history items and writes use the bare number.
+impl<'de> Deserialize<'de> for Priority {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bare(#[serde(deserialize_with = "string_or_number::deserialize")] String),
+            Object { id: String },
+            Named { priority: String },
+        }
+
+        let (Repr::Bare(s) | Repr::Object { id: s } | Repr::Named { priority: s }) =
+            Repr::deserialize(deserializer)?;
+        let priority = s
+            .parse()
+            .ok()
+            .and_then(Self::from_number)
+            .or_else(|| Self::from_name(&s));
+        priority.ok_or_else(|| serde::de::Error::custom("invalid priority"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_full_task() {
+        let task: Task = serde_json::from_value(serde_json::json!({
+            "id": "36w7wbr",
+            "custom_id": null,
+            "name": "Milestone field",
+            "text_content": "Move tasks",
+            "description": "Move tasks",
+            "status": { "status": "in progress", "color": "#a875ff", "orderindex": 1, "type": "custom" },
+            "orderindex": "4.00000000000000000000000000000000",
+            "date_created": "1642734938456",
+            "date_updated": "1642735012018",
+            "date_closed": null,
+            "date_done": null,
+            "archived": false,
+            "creator": { "id": 38221385, "username": "Karel", "color": "#7b68ee", "email": "karel@example.com", "profilePicture": null },
+            "assignees": [{ "id": 38221385, "username": "Karel", "color": "#7b68ee", "initials": "K", "email": "karel@example.com", "profilePicture": null }],
+            "watchers": [],
+            "checklists": [{
+                "id": "b955c4dc-b8a8-48d8-a0c6-b4200788a683",
+                "task_id": "36w7wbr",
+                "name": "Checklist",
+                "date_created": "1642734980123",
+                "orderindex": 0,
+                "creator": 38221385,
+                "resolved": 1,
+                "unresolved": 1,
+                "items": [
+                    { "id": "21e08dc8", "name": "Done", "orderindex": 0, "assignee": null, "resolved": true, "parent": null, "date_created": "1642734990123", "children": [] },
+                    { "id": "4ab0e6c8", "name": "To do", "orderindex": 1, "assignee": null, "resolved": false, "parent": null, "date_created": "1642734991123", "children": [] }
+                ]
+            }],
+            "tags": [{ "name": "backend", "tag_fg": "#800000", "tag_bg": "#800000", "creator": 38221385 }],
+            "parent": "36pnwzu",
+            "priority": { "id": "2", "priority": "high", "color": "#ffcc00", "orderindex": "2" },
+            "due_date": "1643007600000",
+            "start_date": null,
+            "points": null,
+            "time_estimate": 5400000,
+            "time_spent": null,
+            "custom_fields": [],
+            "dependencies": [{ "task_id": "36w7wbr", "depends_on": "36w83z6", "type": 1, "date_created": "1642735000000", "userid": "38221385" }],
+            "linked_tasks": [{ "task_id": "36w7wbr", "link_id": "36w861w", "date_created": "1642735000000", "userid": "38221385" }],
+            "team_id": "20131398",
+            "url": "https://app.clickup.com/t/36w7wbr",
+            "permission_level": "create",
+            "list": { "id": "188335750", "name": "picasso", "access": true },
+            "project": { "id": "115982530", "name": "hidden", "hidden": true, "access": true },
+            "folder": { "id": "115982530", "name": "hidden", "hidden": true, "access": true },
+            "space": { "id": "32279886" },
+            "locations": [{ "id": "188335476", "name": "Milestones" }]
+        }))
+        .unwrap();
+
+        assert_eq!(task.status.unwrap().kind, StatusType::Custom);
+        assert_eq!(task.assignees[0].id, 38221385.into());
+        assert_eq!(task.tags[0].name, "backend");
+        assert_eq!(task.priority, Some(Priority::High));
+        assert_eq!(
+            task.due_date,
+            Some(Utc.timestamp_millis_opt(1643007600000).unwrap())
+        );
+        assert_eq!(task.time_estimate, Some(Duration::minutes(90)));
+        assert_eq!(task.checklists[0].items.len(), 2);
+        assert!(task.checklists[0].items[0].resolved);
+        assert_eq!(task.dependencies[0].depends_on, TaskId::from("36w83z6"));
+        assert_eq!(task.linked_tasks[0].link_id, TaskId::from("36w861w"));
+        assert_eq!(task.team_id, Some(TeamId::from(20131398)));
+        assert!(task.folder.hidden);
+    }
+
+    #[test]
+    fn builds_task_writes() {
+        let due = Utc.timestamp_millis_opt(1643007600000).unwrap();
+
+        let task = NewTask::new("Milestone field")
+            .markdown("Move **all** tasks")
+            .assignee(UserId(38221385))
+            .priority(Priority::High)
+            .due_date(due)
+            .parent(TaskId::from("36pnwzu"));
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "name": "Milestone field",
+                "markdown_content": "Move **all** tasks",
+                "assignees": [38221385],
+                "priority": 2,
+                "due_date": 1643007600000i64,
+                "due_date_time": true,
+                "parent": "36pnwzu",
+            })
+        );
+
+        let update = TaskUpdate::new()
+            .status("review")
+            .priority(None)
+            .due_date(None)
+            .remove_assignee(UserId(38221385));
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({
+                "status": "review",
+                "priority": null,
+                "due_date": null,
+                "assignees": { "add": [], "rem": [38221385] },
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(TaskUpdate::new()).unwrap(),
+            serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn priority_from_number_or_name() {
+        assert_eq!(
+            serde_json::from_str::<Priority>("1").unwrap(),
+            Priority::Urgent
+        );
+        assert_eq!(
+            serde_json::from_str::<Priority>("\"low\"").unwrap(),
+            Priority::Low
+        );
+        assert_eq!(serde_json::to_string(&Priority::Normal).unwrap(), "3");
+    }
 }
@@ -1,9 +1,47 @@
-#[derive(Copy, Clone, serde::Serialize, serde::Deserialize, Hash)]
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::string_or_number;
+
+/// Id of a team, which the ClickUp UI calls a workspace.
+///
+/// ClickUp sends team ids as numbers in some responses and as strings in others.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, Hash, PartialEq, Eq)]
 #[serde(transparent)]
-pub struct TeamId(pub(crate) u128);
+pub struct TeamId(#[serde(deserialize_with = "string_or_number::deserialize")] pub(crate) u64);
 
-impl From<u128> for TeamId {
-    fn from(n: u128) -> Self {
+impl From<u64> for TeamId {
+    fn from(n: u64) -> Self {
         Self(n)
     }
 }
+
+impl fmt::Display for TeamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Team {
+    pub id: TeamId,
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn team_id_from_number_or_string() {
+        assert_eq!(
+            serde_json::from_str::<TeamId>("20131398").unwrap(),
+            TeamId(20131398)
+        );
+        assert_eq!(
+            serde_json::from_str::<TeamId>("\"20131398\"").unwrap(),
+            TeamId(20131398)
+        );
+    }
+}
@@ -0,0 +1,620 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::ClickupClient,
+    duration,
+    error::ClickupError,
+    folder::FolderId,
+    list::ListId,
+    search::TaskFilter,
+    space::SpaceId,
+    tag::Tag,
+    task::{Task, TaskId},
+    team::TeamId,
+    timestamp,
+    user::{User, UserId},
+};
+
+#[derive(Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct TimeEntryId(pub(crate) String);
+
+impl From<&str> for TimeEntryId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl fmt::Display for TimeEntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Time a user tracked, on a task or on nothing in particular.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub id: TimeEntryId,
+    pub task: Option<TimeEntryTask>,
+    /// The team the time was tracked in.
+    pub wid: Option<TeamId>,
+    pub user: User,
+    #[serde(default)]
+    pub billable: bool,
+    #[serde(with = "timestamp")]
+    pub start: DateTime<Utc>,
+    #[serde(default, with = "timestamp::option")]
+    pub end: Option<DateTime<Utc>>,
+    /// Negative while the timer is running.
+    #[serde(with = "duration")]
+    pub duration: Duration,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+impl TimeEntry {
+    pub fn is_running(&self) -> bool {
+        self.duration < Duration::zero()
+    }
+
+    /// The tracked time, up to now for a running timer.
+    pub fn tracked(&self) -> Duration {
+        if self.is_running() {
+            Utc::now() - self.start
+        } else {
+            self.duration
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeEntryTask {
+    pub id: TaskId,
+    pub name: Option<String>,
+}
+
+/// Which time entries to get, by default those of the last 30 days of the token's user.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeEntryFilter {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// Users whose entries to get, which requires the token's user to be an admin.
+    pub assignees: Vec<UserId>,
+    /// Narrows the entries down to one of these, the most specific one set.
+    pub task: Option<TaskId>,
+    pub list: Option<ListId>,
+    pub folder: Option<FolderId>,
+    pub space: Option<SpaceId>,
+}
+
+impl TimeEntryFilter {
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+
+        if let Some(start) = self.start {
+            query.push(("start_date", start.timestamp_millis().to_string()));
+        }
+        if let Some(end) = self.end {
+            query.push(("end_date", end.timestamp_millis().to_string()));
+        }
+        if !self.assignees.is_empty() {
+            let assignees: Vec<_> = self.assignees.iter().map(UserId::to_string).collect();
+            query.push(("assignee", assignees.join(",")));
+        }
+
+        let location = match (&self.task, &self.list, &self.folder, &self.space) {
+            (Some(task), ..) => Some(("task_id", task.0.clone())),
+            (_, Some(list), ..) => Some(("list_id", list.0.clone())),
+            (_, _, Some(folder), _) => Some(("folder_id", folder.0.clone())),
+            (_, _, _, Some(space)) => Some(("space_id", space.0.clone())),
+            _ => None,
+        };
+        query.extend(location);
+
+        query
+    }
+}
+
+/// Time to track after the fact, built up from when it started and how long it took.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewTimeEntry {
+    #[serde(serialize_with = "timestamp::millis::serialize")]
+    start: Option<DateTime<Utc>>,
+    #[serde(with = "duration")]
+    duration: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tid: Option<TaskId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    billable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<UserId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<TagName>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct TagName {
+    name: String,
+}
+
+impl NewTimeEntry {
+    pub fn new(start: DateTime<Utc>, duration: Duration) -> Self {
+        Self {
+            start: Some(start),
+            duration,
+            tid: None,
+            description: None,
+            billable: false,
+            assignee: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn task(mut self, task: TaskId) -> Self {
+        self.tid = Some(task);
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn billable(mut self) -> Self {
+        self.billable = true;
+        self
+    }
+
+    /// Tracks the time for another user, which requires the token's user to be an admin.
+    pub fn assignee(mut self, user: UserId) -> Self {
+        self.assignee = Some(user);
+        self
+    }
+
+    pub fn tag(mut self, name: impl Into<String>) -> Self {
+        self.tags.push(TagName { name: name.into() });
+        self
+    }
+}
+
+/// Changes to a time entry, everything not set is left as it is.
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct TimeEntryUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    start: Option<DateTime<Utc>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamp::millis::serialize"
+    )]
+    end: Option<DateTime<Utc>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "duration::option::serialize"
+    )]
+    duration: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tid: Option<TaskId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    billable: Option<bool>,
+}
+
+impl TimeEntryUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn end(mut self, end: DateTime<Utc>) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Moves the entry to another task.
+    pub fn task(mut self, task: TaskId) -> Self {
+        self.tid = Some(task);
+        self
+    }
+
+    pub fn billable(mut self, billable: bool) -> Self {
+        self.billable = Some(billable);
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct StartTimerParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tid: Option<&'a TaskId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct TimeEntriesResponse {
+    data: Vec<TimeEntry>,
+}
+
+#[derive(Deserialize)]
+struct TimeEntryResponse<T = TimeEntry> {
+    data: T,
+}
+
+/// The time estimated for and spent on a task and all its subtasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeRollup {
+    #[serde(with = "duration")]
+    pub estimate: Duration,
+    #[serde(with = "duration")]
+    pub spent: Duration,
+    /// Number of tasks summed up, including the task itself.
+    pub tasks: usize,
+}
+
+impl Default for TimeRollup {
+    fn default() -> Self {
+        Self {
+            estimate: Duration::zero(),
+            spent: Duration::zero(),
+            tasks: 0,
+        }
+    }
+}
+
+impl TimeRollup {
+    /// Sums up `task` and its subtasks among `tasks`, however deeply nested.
+    pub fn of(task: &Task, tasks: &[Task]) -> Self {
+        let mut subtasks: HashMap<&TaskId, Vec<&Task>> = HashMap::new();
+        for subtask in tasks {
+            if let Some(parent) = &subtask.parent {
+                subtasks.entry(parent).or_default().push(subtask);
+            }
+        }
+
+        let mut rollup = Self::default();
+        rollup.add(task);
+
+        let mut seen = HashSet::from([&task.id]);
+        let mut parents = vec![&task.id];
+        while let Some(parent) = parents.pop() {
+            for subtask in subtasks.get(parent).into_iter().flatten() {
+                if seen.insert(&subtask.id) {
+                    rollup.add(subtask);
+                    parents.push(&subtask.id);
+                }
+            }
+        }
+
+        rollup
+    }
+
+    fn add(&mut self, task: &Task) {
+        self.estimate = self.estimate + task.time_estimate.unwrap_or_else(Duration::zero);
+        self.spent = self.spent + task.time_spent.unwrap_or_else(Duration::zero);
+        self.tasks += 1;
+    }
+}
+
+impl ClickupClient {
+    /// Gets the time entries of the team matching `filter`, most recent first.
+    pub async fn get_time_entries(
+        &self,
+        team: TeamId,
+        filter: &TimeEntryFilter,
+    ) -> Result<Vec<TimeEntry>, ClickupError> {
+        let response: TimeEntriesResponse = self
+            .send_json(
+                self.get(&format!("/team/{team}/time_entries"))
+                    .query(&filter.query()),
+            )
+            .await?;
+
+        Ok(response.data)
+    }
+
+    pub async fn get_time_entry(
+        &self,
+        team: TeamId,
+        id: &TimeEntryId,
+    ) -> Result<TimeEntry, ClickupError> {
+        let response: TimeEntryResponse = self
+            .send_json(self.get(&format!("/team/{team}/time_entries/{id}")))
+            .await?;
+
+        Ok(response.data)
+    }
+
+    /// Gets the entry of the token user's running timer, if it has one.
+    pub async fn get_running_time_entry(
+        &self,
+        team: TeamId,
+    ) -> Result<Option<TimeEntry>, ClickupError> {
+        let response: TimeEntryResponse<Option<TimeEntry>> = self
+            .send_json(self.get(&format!("/team/{team}/time_entries/current")))
+            .await?;
+
+        Ok(response.data)
+    }
+
+    pub async fn create_time_entry(
+        &self,
+        team: TeamId,
+        entry: &NewTimeEntry,
+    ) -> Result<TimeEntry, ClickupError> {
+        let response: TimeEntryResponse = self
+            .send_json(self.post(&format!("/team/{team}/time_entries")).json(entry))
+            .await?;
+        self.invalidate_entry_task(&response.data);
+
+        Ok(response.data)
+    }
+
+    /// Updates an entry. The entry is fetched first, to learn which task's time spent changes.
+    pub async fn update_time_entry(
+        &self,
+        team: TeamId,
+        id: &TimeEntryId,
+        update: &TimeEntryUpdate,
+    ) -> Result<(), ClickupError> {
+        let previous = self.get_time_entry(team, id).await?;
+        let result = self
+            .send(
+                self.put(&format!("/team/{team}/time_entries/{id}"))
+                    .json(update),
+            )
+            .await;
+        self.invalidate_entry_task(&previous);
+        if let Some(task) = &update.tid {
+            self.connection().tasks().invalidate(task);
+        }
+        result
+    }
+
+    /// Deletes an entry. The entry is fetched first, to learn which task's time spent changes.
+    pub async fn delete_time_entry(
+        &self,
+        team: TeamId,
+        id: &TimeEntryId,
+    ) -> Result<(), ClickupError> {
+        let previous = self.get_time_entry(team, id).await?;
+        let result = self
+            .send(self.delete(&format!("/team/{team}/time_entries/{id}")))
+            .await;
+        self.invalidate_entry_task(&previous);
+        result
+    }
+
+    /// Starts a timer for the token's user, on `task` if given.
+    pub async fn start_timer(
+        &self,
+        team: TeamId,
+        task: Option<&TaskId>,
+        description: Option<&str>,
+    ) -> Result<TimeEntry, ClickupError> {
+        let response: TimeEntryResponse = self
+            .send_json(self.post(&format!("/team/{team}/time_entries/start")).json(
+                &StartTimerParams {
+                    tid: task,
+                    description,
+                },
+            ))
+            .await?;
+
+        Ok(response.data)
+    }
+
+    /// Stops the token user's running timer, returning the finished entry.
+    pub async fn stop_timer(&self, team: TeamId) -> Result<TimeEntry, ClickupError> {
+        let response: TimeEntryResponse = self
+            .send_json(self.post(&format!("/team/{team}/time_entries/stop")))
+            .await?;
+        self.invalidate_entry_task(&response.data);
+
+        Ok(response.data)
+    }
+
+    /// Sums up the time estimated for and spent on `task` and all its subtasks, however deeply
+    /// nested. See [`Self::time_rollups`].
+    pub async fn time_rollup(&self, team: TeamId, task: &Task) -> Result<TimeRollup, ClickupError> {
+        let rollups = self.time_rollups(team, std::slice::from_ref(task)).await?;
+        Ok(rollups[0])
+    }
+
+    /// Sums up the time estimated for and spent on each of `tasks` and all their subtasks.
+    ///
+    /// All tasks of the spaces the tasks are in are fetched once, taking a request for every 100
+    /// tasks in those spaces, and the subtasks are found among them.
+    pub async fn time_rollups(
+        &self,
+        team: TeamId,
+        tasks: &[Task],
+    ) -> Result<Vec<TimeRollup>, ClickupError> {
+        let mut spaces: HashMap<&SpaceId, Vec<Task>> = HashMap::new();
+        for task in tasks {
+            if spaces.contains_key(&task.space.id) {
+                continue;
+            }
+
+            let filter = TaskFilter {
+                subtasks: true,
+                include_closed: true,
+                space_ids: vec![task.space.id.0.clone()],
+                ..Default::default()
+            };
+            let space_tasks = self
+                .get_filtered_team_tasks(team, &filter)
+                .try_collect()
+                .await?;
+            spaces.insert(&task.space.id, space_tasks);
+        }
+
+        Ok(tasks
+            .iter()
+            .map(|task| TimeRollup::of(task, &spaces[&task.space.id]))
+            .collect())
+    }
+
+    fn invalidate_entry_task(&self, entry: &TimeEntry) {
+        if let Some(task) = &entry.task {
+            self.connection().tasks().invalidate(&task.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_time_entries() {
+        let entry: TimeEntry = serde_json::from_value(json!({
+            "id": "1963465985517105840",
+            "task": { "id": "36w7wbr", "name": "Milestone field", "status": { "status": "in progress" } },
+            "wid": "20131398",
+            "user": { "id": 38221385, "username": "Karel" },
+            "billable": false,
+            "start": "1642735012018",
+            "end": "1642738612018",
+            "duration": "3600000",
+            "description": "",
+            "tags": [],
+            "source": "clickup",
+            "at": "1642738612018"
+        }))
+        .unwrap();
+
+        assert_eq!(entry.task.as_ref().unwrap().id, TaskId::from("36w7wbr"));
+        assert_eq!(entry.wid, Some(TeamId::from(20131398)));
+        assert_eq!(entry.tracked(), Duration::hours(1));
+
+        let running: TimeEntry = serde_json::from_value(json!({
+            "id": "1963465985517105841",
+            "task": null,
+            "user": { "id": 38221385 },
+            "start": "1642735012018",
+            "end": null,
+            "duration": "-1642735012018"
+        }))
+        .unwrap();
+
+        assert!(running.is_running());
+        assert!(running.tracked() > Duration::hours(1));
+    }
+
+    #[test]
+    fn builds_time_entry_query() {
+        let filter = TimeEntryFilter {
+            start: Some(Utc.timestamp_millis_opt(1642735012018).unwrap()),
+            assignees: vec![UserId(38221385), UserId(38221386)],
+            list: Some(ListId::from("188335750")),
+            space: Some(SpaceId::from("32279886")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter.query(),
+            vec![
+                ("start_date", "1642735012018".to_owned()),
+                ("assignee", "38221385,38221386".to_owned()),
+                ("list_id", "188335750".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_new_time_entry() {
+        let entry = NewTimeEntry::new(
+            Utc.timestamp_millis_opt(1642735012018).unwrap(),
+            Duration::minutes(90),
+        )
+        .task(TaskId::from("36w7wbr"))
+        .tag("review");
+
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({
+                "start": 1642735012018i64,
+                "duration": 5400000,
+                "tid": "36w7wbr",
+                "billable": false,
+                "tags": [{ "name": "review" }]
+            })
+        );
+    }
+
+    #[test]
+    fn writes_only_changed_parts_of_time_entry() {
+        let update = TimeEntryUpdate::new()
+            .duration(Duration::minutes(45))
+            .task(TaskId::from("36w83z6"));
+
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({ "duration": 2700000, "tid": "36w83z6" })
+        );
+        assert_eq!(
+            serde_json::to_value(TimeEntryUpdate::new()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn rolls_up_estimates_of_nested_subtasks() {
+        let task = |id: &str, parent: Option<&str>, estimate: i64, spent: i64| Task {
+            id: TaskId::from(id),
+            parent: parent.map(TaskId::from),
+            time_estimate: Some(Duration::minutes(estimate)),
+            time_spent: Some(Duration::minutes(spent)),
+            ..Default::default()
+        };
+        let milestone = task("36pnwzu", None, 0, 0);
+        let tasks = [
+            milestone.clone(),
+            task("36w7wbr", Some("36pnwzu"), 120, 30),
+            task("36w83z6", Some("36w7wbr"), 60, 45),
+            task("36w8a1k", Some("36w83z6"), 0, 15),
+            task("36w9cde", None, 480, 480),
+            task("36w9cdf", Some("36w9cde"), 60, 60),
+        ];
+
+        assert_eq!(
+            TimeRollup::of(&milestone, &tasks),
+            TimeRollup {
+                estimate: Duration::minutes(180),
+                spent: Duration::minutes(90),
+                tasks: 4,
+            }
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! (De)serializes the millisecond unix timestamps ClickUp uses, for `#[serde(with = "...")]`.
+//!
+//! ClickUp sends them as strings in most places and as numbers in some, both are accepted.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de::Error, Deserializer, Serializer};
+
+use super::string_or_number;
+
+fn from_millis<E: Error>(millis: i64) -> Result<DateTime<Utc>, E> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| E::custom(format!("invalid timestamp {millis}")))
+}
+
+pub fn serialize<S: Serializer>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&date.timestamp_millis().to_string())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    from_millis(string_or_number::deserialize(deserializer)?)
+}
+
+/// For optional timestamps, which ClickUp sends as `null` when unset.
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => super::serialize(date, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        string_or_number::option::deserialize(deserializer)?
+            .map(from_millis)
+            .transpose()
+    }
+}
+
+/// For optional timestamps in request bodies, which ClickUp expects as numbers.
+pub mod millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_i64(date.timestamp_millis()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// For updates, where `Some(None)` clears the timestamp and `None` is left out with
+    /// `skip_serializing_if = "Option::is_none"`.
+    pub mod clearable {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            date: &Option<Option<DateTime<Utc>>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::serialize(&date.flatten(), serializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Dates {
+        #[serde(with = "super")]
+        date: DateTime<Utc>,
+        #[serde(default, with = "super::option")]
+        due_date: Option<DateTime<Utc>>,
+    }
+
+    #[test]
+    fn reads_strings_and_numbers() {
+        let expected = Utc.timestamp_millis_opt(1642735012018).unwrap();
+
+        let dates: Dates =
+            serde_json::from_str(r#"{"date":"1642735012018","due_date":1642735012018}"#).unwrap();
+        assert_eq!(dates.date, expected);
+        assert_eq!(dates.due_date, Some(expected));
+
+        let dates: Dates =
+            serde_json::from_str(r#"{"date":"1642735012018","due_date":null}"#).unwrap();
+        assert_eq!(dates.due_date, None);
+    }
+
+    #[test]
+    fn roundtrips_as_string() {
+        let dates = Dates {
+            date: Utc.timestamp_millis_opt(1642735012018).unwrap(),
+            due_date: None,
+        };
+
+        let json = serde_json::to_string(&dates).unwrap();
+        assert_eq!(json, r#"{"date":"1642735012018","due_date":null}"#);
+        assert_eq!(serde_json::from_str::<Dates>(&json).unwrap(), dates);
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::string_or_number;
+
+/// Like team ids, ClickUp sends user ids as strings in some places, such as dependencies.
+#[derive(
+    Copy, Clone, Serialize, Deserialize, Hash, Default, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+#[serde(transparent)]
+pub struct UserId(#[serde(deserialize_with = "string_or_number::deserialize")] pub(crate) u64);
+
+impl From<u64> for UserId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: UserId,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub color: Option<String>,
+    pub initials: Option<String>,
+    pub profile_picture: Option<String>,
+}
@@ -1,6 +1,7 @@
 pub mod request {
     use super::events::Event;
-    use crate::clickup::{auth::ClickupToken, team::TeamId};
+    use crate::clickup::{client::ClickupClient, error::ClickupError, team::TeamId};
+    use enumset::EnumSet;
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Clone, Hash)]
@@ -53,6 +54,7 @@ pub mod request {
         pub endpoint: String,
         #[serde(rename = "client_id")]
         pub client_id: Option<String>,
+        #[serde(deserialize_with = "super::events::deserialize_events")]
         pub events: Vec<Event>,
         #[serde(rename = "task_id")]
         pub task_id: Option<String>,
@@ -62,13 +64,89 @@ pub mod request {
         pub folder_id: Option<String>,
         #[serde(rename = "space_id")]
         pub space_id: Option<String>,
-        // pub health: Health,
+        #[serde(default)]
+        pub health: Health,
         pub secret: Option<String>,
     }
 
-    // #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-    // #[serde(rename_all = "camelCase")]
-    // pub struct Health {}
+    impl Webhook {
+        pub fn events(&self) -> EnumSet<Event> {
+            self.events.iter().copied().collect()
+        }
+    }
+
+    /// ClickUp marks a webhook as failing when deliveries fail, and suspends it after 100 failures.
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Health {
+        pub status: WebhookStatus,
+        #[serde(rename = "fail_count")]
+        pub fail_count: u64,
+    }
+
+    #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum WebhookStatus {
+        #[default]
+        Active,
+        Failing,
+        Suspended,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ListWebhooksResponse {
+        pub webhooks: Vec<Webhook>,
+    }
+
+    #[derive(Serialize, Clone)]
+    pub struct UpdateWebhookParameters {
+        pub endpoint: String,
+        pub events: Vec<Event>,
+        /// Setting a suspended webhook to active re-enables it.
+        pub status: WebhookStatus,
+    }
+
+    /// What [`ClickupClient::ensure_webhook`] has to do to end up with exactly one webhook for an endpoint.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Reconciliation<'a> {
+        Create,
+        Keep {
+            webhook: &'a Webhook,
+            update: bool,
+            delete: Vec<&'a Webhook>,
+        },
+    }
+
+    impl<'a> Reconciliation<'a> {
+        /// Plans the changes for the webhooks of `endpoint` among the `existing` ones.
+        ///
+        /// A webhook that already has the wanted events is preferred, any other webhooks for the
+        /// endpoint are duplicates that get deleted. A kept webhook that is not active is updated
+        /// to re-enable it.
+        pub fn plan(existing: &'a [Webhook], endpoint: &str, events: EnumSet<Event>) -> Self {
+            let mut matching: Vec<_> = existing
+                .iter()
+                .filter(|webhook| webhook.endpoint == endpoint)
+                .collect();
+
+            if matching.is_empty() {
+                return Self::Create;
+            }
+
+            let keep = matching
+                .iter()
+                .position(|webhook| webhook.events() == events)
+                .unwrap_or(0);
+            let webhook = matching.remove(keep);
+
+            Self::Keep {
+                webhook,
+                update: webhook.events() != events
+                    || webhook.health.status != WebhookStatus::Active,
+                delete: matching,
+            }
+        }
+    }
 
     impl<U: Into<String>, V: Into<enumset::EnumSet<Event>>> From<(U, V)> for CreateWebhookParameters {
         fn from(params: (U, V)) -> Self {
@@ -84,43 +162,712 @@ pub mod request {
         }
     }
 
-    pub async fn create_webhook(
-        token: &ClickupToken,
-        team_id: impl Into<TeamId>,
-        params: impl Into<CreateWebhookParameters>,
-    ) -> Result<String, reqwest::Error> {
-        let params: CreateWebhookParametersInner = params.into().into();
-        let client = reqwest::Client::new();
+    impl ClickupClient {
+        pub async fn create_webhook(
+            &self,
+            team_id: impl Into<TeamId>,
+            params: impl Into<CreateWebhookParameters>,
+        ) -> Result<CreateWebhookResponse, ClickupError> {
+            let params: CreateWebhookParametersInner = params.into().into();
 
-        let url = format!(
-            "https://api.clickup.com/api/v2/team/{}/webhook",
-            team_id.into().0
-        );
-
-        client
-            .post(url)
-            .header(reqwest::header::AUTHORIZATION, token.0)
-            .json(&params)
-            .send()
-            .await?
-            .text()
+            self.send_json(
+                self.post(&format!("/team/{}/webhook", team_id.into().0))
+                    .json(&params),
+            )
             .await
+        }
+
+        pub async fn list_webhooks(
+            &self,
+            team_id: impl Into<TeamId>,
+        ) -> Result<Vec<Webhook>, ClickupError> {
+            let response: ListWebhooksResponse = self
+                .send_json(self.get(&format!("/team/{}/webhook", team_id.into().0)))
+                .await?;
+
+            Ok(response.webhooks)
+        }
+
+        pub async fn update_webhook(
+            &self,
+            webhook_id: &str,
+            params: UpdateWebhookParameters,
+        ) -> Result<CreateWebhookResponse, ClickupError> {
+            self.send_json(self.put(&format!("/webhook/{}", webhook_id)).json(&params))
+                .await
+        }
+
+        pub async fn delete_webhook(&self, webhook_id: &str) -> Result<(), ClickupError> {
+            self.send(self.delete(&format!("/webhook/{}", webhook_id)))
+                .await
+        }
+
+        /// Makes sure the team has exactly one active webhook for `endpoint`, subscribed to
+        /// `events`.
+        ///
+        /// Reuses an existing webhook for the endpoint where possible instead of creating a new
+        /// one on every start, updating its events if they changed, re-enabling it when it was
+        /// suspended and deleting any duplicates.
+        pub async fn ensure_webhook(
+            &self,
+            team_id: impl Into<TeamId>,
+            endpoint: &str,
+            events: EnumSet<Event>,
+        ) -> Result<Webhook, ClickupError> {
+            let team_id = team_id.into();
+            let existing = self.list_webhooks(team_id).await?;
+
+            let (webhook, update, delete) = match Reconciliation::plan(&existing, endpoint, events)
+            {
+                Reconciliation::Create => {
+                    let response = self.create_webhook(team_id, (endpoint, events)).await?;
+                    tracing::info!("created webhook {} for {}", response.id, endpoint);
+                    return Ok(response.webhook);
+                }
+                Reconciliation::Keep {
+                    webhook,
+                    update,
+                    delete,
+                } => (webhook, update, delete),
+            };
+
+            for duplicate in delete {
+                self.delete_webhook(&duplicate.id).await?;
+                tracing::info!(
+                    "deleted duplicate webhook {} for {}",
+                    duplicate.id,
+                    endpoint
+                );
+            }
+
+            if !update {
+                tracing::info!("reusing webhook {} for {}", webhook.id, endpoint);
+                return Ok(webhook.clone());
+            }
+
+            if webhook.health.status != WebhookStatus::Active {
+                tracing::warn!(
+                    "re-enabling {:?} webhook {} for {} after {} failures",
+                    webhook.health.status,
+                    webhook.id,
+                    endpoint,
+                    webhook.health.fail_count
+                );
+            }
+
+            let params = UpdateWebhookParameters {
+                endpoint: endpoint.to_owned(),
+                events: events.into_iter().collect(),
+                status: WebhookStatus::Active,
+            };
+            let response = self.update_webhook(&webhook.id, params).await?;
+            tracing::info!("updated webhook {} for {}", webhook.id, endpoint);
+
+            // The update response does not include the secret, which did not change.
+            Ok(Webhook {
+                secret: response.webhook.secret.or_else(|| webhook.secret.clone()),
+                ..response.webhook
+            })
+        }
     }
 
     #[cfg(test)]
     mod tests {
 
         use super::*;
+        use crate::clickup::{
+            auth::ClickupToken,
+            client::{ClickupConnection, ClientConfig},
+        };
+
+        fn webhook(id: &str, endpoint: &str, events: EnumSet<Event>) -> Webhook {
+            Webhook {
+                id: id.into(),
+                endpoint: endpoint.into(),
+                events: events.into_iter().collect(),
+                ..Default::default()
+            }
+        }
+
+        const ENDPOINT: &str = "https://clickity.fly.dev/webhook/clickup_id";
+
+        #[test]
+        fn plans_creation_without_existing_webhook() {
+            let existing = [webhook("a", "https://example.com/webhook", Event::all())];
+
+            assert_eq!(
+                Reconciliation::plan(&existing, ENDPOINT, Event::all()),
+                Reconciliation::Create
+            );
+        }
+
+        #[test]
+        fn plans_reuse_and_deletes_duplicates() {
+            let existing = [
+                webhook("a", ENDPOINT, Event::TaskCreated.into()),
+                webhook("b", ENDPOINT, Event::all()),
+                webhook("c", "https://example.com/webhook", Event::all()),
+                webhook("d", ENDPOINT, Event::all()),
+            ];
+
+            assert_eq!(
+                Reconciliation::plan(&existing, ENDPOINT, Event::all()),
+                Reconciliation::Keep {
+                    webhook: &existing[1],
+                    update: false,
+                    delete: vec![&existing[0], &existing[3]],
+                }
+            );
+        }
+
+        #[test]
+        fn plans_update_of_changed_events() {
+            let existing = [webhook("a", ENDPOINT, Event::TaskCreated.into())];
+
+            assert_eq!(
+                Reconciliation::plan(&existing, ENDPOINT, Event::all()),
+                Reconciliation::Keep {
+                    webhook: &existing[0],
+                    update: true,
+                    delete: vec![],
+                }
+            );
+        }
+
+        #[test]
+        fn plans_update_of_suspended_webhook() {
+            let mut suspended = webhook("a", ENDPOINT, Event::all());
+            suspended.health = Health {
+                status: WebhookStatus::Suspended,
+                fail_count: 100,
+            };
+            let existing = [suspended];
+
+            assert_eq!(
+                Reconciliation::plan(&existing, ENDPOINT, Event::all()),
+                Reconciliation::Keep {
+                    webhook: &existing[0],
+                    update: true,
+                    delete: vec![],
+                }
+            );
+        }
+
+        #[test]
+        fn deserializes_wildcard_events() {
+            let webhooks: ListWebhooksResponse = serde_json::from_value(serde_json::json!({
+                "webhooks": [{
+                    "id": "4b67ac88-e506-4a29-9d42-26e504e3435e",
+                    "userid": 183,
+                    "team_id": 108,
+                    "endpoint": ENDPOINT,
+                    "client_id": null,
+                    "events": ["*"],
+                    "task_id": null,
+                    "list_id": null,
+                    "folder_id": null,
+                    "space_id": null,
+                    "health": { "status": "failing", "fail_count": 5 },
+                    "secret": "O94IM25S7PXBPYTMNXLLET230SRP0S89"
+                }]
+            }))
+            .unwrap();
+
+            assert_eq!(webhooks.webhooks[0].events(), Event::all());
+            assert_eq!(webhooks.webhooks[0].health.status, WebhookStatus::Failing);
+        }
 
         #[tokio::test]
-        async fn test_create_webhook_works() {
-            create_webhook(
-                &ClickupToken("faketoken"),
-                1,
-                ("https://yourdomain.com/webhook", Event::all()),
+        async fn create_webhook_reports_invalid_token() {
+            use axum::{http::StatusCode, routing::post, Json, Router};
+
+            let app = Router::new().route(
+                "/api/v2/team/:team_id/webhook",
+                post(|| async {
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        Json(serde_json::json!({ "err": "Token invalid", "ECODE": "OAUTH_025" })),
+                    )
+                }),
+            );
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(
+                axum::Server::from_tcp(listener)
+                    .unwrap()
+                    .serve(app.into_make_service()),
+            );
+
+            let connection = ClickupConnection::new(&ClientConfig {
+                base_url: format!("http://{addr}/api/v2"),
+                ..Default::default()
+            })
+            .unwrap();
+            let result = connection
+                .client(ClickupToken("faketoken".into()))
+                .create_webhook(1, ("https://yourdomain.com/webhook", Event::all()))
+                .await;
+
+            assert!(
+                matches!(result, Err(ClickupError::Unauthorized(body)) if body.err == "Token invalid")
+            );
+        }
+    }
+}
+
+pub mod signature {
+    use std::{io, path::PathBuf};
+
+    use hmac::{Hmac, Mac};
+    use serde::{Deserialize, Serialize};
+    use sha2::Sha256;
+
+    use crate::{secret::Secret, store::JsonStore};
+
+    /// Header carrying the signature of a webhook delivery.
+    pub const HEADER: &str = "X-Signature";
+
+    /// Checks `signature`, the hex encoded HMAC-SHA256 of `body` keyed with the webhook's secret.
+    pub fn verify(secret: &Secret, body: &[u8], signature: &str) -> bool {
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose().as_bytes())
+            .expect("hmac accepts keys of any length");
+        mac.update(body);
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    /// The secret ClickUp signs the deliveries of a webhook with.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct WebhookSecret {
+        /// ClickUp's id of the webhook, which deliveries carry as `webhook_id`.
+        pub webhook_id: String,
+        pub secret: Secret,
+    }
+
+    /// Secrets of the webhooks clicky created, keyed by the name of the configured webhook.
+    ///
+    /// Deliveries are matched to a secret by the endpoint they arrive at, never by the
+    /// `webhook_id` in the body, which is only trusted once the signature has been checked.
+    #[derive(Debug)]
+    pub struct WebhookSecrets(JsonStore<String, WebhookSecret>);
+
+    impl WebhookSecrets {
+        pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+            JsonStore::open(path).map(Self)
+        }
+
+        pub fn get(&self, name: &str) -> Option<WebhookSecret> {
+            self.0.get(&name.to_owned())
+        }
+
+        pub fn insert(&self, name: &str, webhook_id: &str, secret: &str) -> io::Result<()> {
+            self.0.insert(
+                name.to_owned(),
+                WebhookSecret {
+                    webhook_id: webhook_id.to_owned(),
+                    secret: Secret::new(secret),
+                },
             )
-            .await
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const BODY: &[u8] =
+            br#"{"event":"taskUpdated","task_id":"36w7wbr","webhook_id":"4b67ac88"}"#;
+
+        fn sign(secret: &str, body: &[u8]) -> String {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+            mac.update(body);
+            hex::encode(mac.finalize().into_bytes())
+        }
+
+        #[test]
+        fn accepts_valid_signature() {
+            let secret = Secret::new("O94IM25S7PXBPYTMNXLLET230SRP0S89");
+
+            assert!(verify(&secret, BODY, &sign(secret.expose(), BODY)));
+        }
+
+        #[test]
+        fn rejects_invalid_signature() {
+            let secret = Secret::new("O94IM25S7PXBPYTMNXLLET230SRP0S89");
+
+            assert!(!verify(&secret, BODY, &sign("other secret", BODY)));
+            assert!(!verify(&secret, b"{}", &sign(secret.expose(), BODY)));
+            assert!(!verify(&secret, BODY, "not hex"));
+        }
+
+        #[test]
+        fn stores_secrets_without_showing_them() {
+            let path = std::env::temp_dir()
+                .join(format!("clicky-secrets-{}", uuid::Uuid::new_v4()))
+                .join("webhooks.json");
+
+            let secrets = WebhookSecrets::open(&path).unwrap();
+            secrets
+                .insert("milestones", "4b67ac88", "O94IM25S7PXBPYTMNXLLET230SRP0S89")
+                .unwrap();
+            assert!(!format!("{secrets:?}").contains("O94IM25S7PXBPYTMNXLLET230SRP0S89"));
+
+            let stored = WebhookSecrets::open(&path)
+                .unwrap()
+                .get("milestones")
+                .unwrap();
+            assert_eq!(stored.webhook_id, "4b67ac88");
+            assert_eq!(stored.secret.expose(), "O94IM25S7PXBPYTMNXLLET230SRP0S89");
+
+            std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        }
+    }
+}
+
+pub mod payload {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+
+    use super::events::Event;
+    use crate::clickup::{
+        custom_field::CustomField, folder::FolderId, list::ListId, space::SpaceId, task::TaskId,
+        timestamp, user::User, writes::OwnWrites,
+    };
+
+    /// The body ClickUp posts to a webhook endpoint, one variant per [`Event`].
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "event", rename_all = "camelCase")]
+    pub enum Payload {
+        TaskCreated(TaskPayload),
+        TaskUpdated(TaskPayload),
+        TaskDeleted(TaskPayload),
+        TaskPriorityUpdated(TaskPayload),
+        TaskStatusUpdated(TaskPayload),
+        TaskAssigneeUpdated(TaskPayload),
+        TaskDueDateUpdated(TaskPayload),
+        TaskTagUpdated(TaskPayload),
+        TaskMoved(TaskPayload),
+        TaskCommentPosted(TaskPayload),
+        TaskCommentUpdated(TaskPayload),
+        TaskTimeEstimateUpdated(TaskPayload),
+        TaskTimeTrackedUpdated(TaskPayload),
+        ListCreated(ListPayload),
+        ListUpdated(ListPayload),
+        ListDeleted(ListPayload),
+        FolderCreated(FolderPayload),
+        FolderUpdated(FolderPayload),
+        FolderDeleted(FolderPayload),
+        SpaceCreated(SpacePayload),
+        SpaceUpdated(SpacePayload),
+        SpaceDeleted(SpacePayload),
+        GoalCreated(GoalPayload),
+        GoalUpdated(GoalPayload),
+        GoalDeleted(GoalPayload),
+        KeyResultCreated(KeyResultPayload),
+        KeyResultUpdated(KeyResultPayload),
+        KeyResultDeleted(KeyResultPayload),
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct TaskPayload {
+        pub webhook_id: String,
+        pub task_id: TaskId,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ListPayload {
+        pub webhook_id: String,
+        pub list_id: ListId,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct FolderPayload {
+        pub webhook_id: String,
+        pub folder_id: FolderId,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SpacePayload {
+        pub webhook_id: String,
+        pub space_id: SpaceId,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct GoalPayload {
+        pub webhook_id: String,
+        pub goal_id: String,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct KeyResultPayload {
+        pub webhook_id: String,
+        pub key_result_id: String,
+        #[serde(default)]
+        pub history_items: Vec<HistoryItem>,
+    }
+
+    /// A single change that caused the event.
+    ///
+    /// `field` names what changed, such as `status`, `parent` or `custom_field`. The shape of
+    /// `before` and `after` depends on the field, for custom fields the changed field itself is
+    /// included as `custom_field`.
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct HistoryItem {
+        pub id: String,
+        #[serde(with = "timestamp")]
+        pub date: DateTime<Utc>,
+        pub field: String,
+        pub parent_id: Option<String>,
+        pub user: Option<User>,
+        #[serde(default)]
+        pub before: Value,
+        #[serde(default)]
+        pub after: Value,
+        pub custom_field: Option<CustomField>,
+    }
+
+    impl Payload {
+        pub fn event(&self) -> Event {
+            match self {
+                Payload::TaskCreated(_) => Event::TaskCreated,
+                Payload::TaskUpdated(_) => Event::TaskUpdated,
+                Payload::TaskDeleted(_) => Event::TaskDeleted,
+                Payload::TaskPriorityUpdated(_) => Event::TaskPriorityUpdated,
+                Payload::TaskStatusUpdated(_) => Event::TaskStatusUpdated,
+                Payload::TaskAssigneeUpdated(_) => Event::TaskAssigneeUpdated,
+                Payload::TaskDueDateUpdated(_) => Event::TaskDueDateUpdated,
+                Payload::TaskTagUpdated(_) => Event::TaskTagUpdated,
+                Payload::TaskMoved(_) => Event::TaskMoved,
+                Payload::TaskCommentPosted(_) => Event::TaskCommentPosted,
+                Payload::TaskCommentUpdated(_) => Event::TaskCommentUpdated,
+                Payload::TaskTimeEstimateUpdated(_) => Event::TaskTimeEstimateUpdated,
+                Payload::TaskTimeTrackedUpdated(_) => Event::TaskTimeTrackedUpdated,
+                Payload::ListCreated(_) => Event::ListCreated,
+                Payload::ListUpdated(_) => Event::ListUpdated,
+                Payload::ListDeleted(_) => Event::ListDeleted,
+                Payload::FolderCreated(_) => Event::FolderCreated,
+                Payload::FolderUpdated(_) => Event::FolderUpdated,
+                Payload::FolderDeleted(_) => Event::FolderDeleted,
+                Payload::SpaceCreated(_) => Event::SpaceCreated,
+                Payload::SpaceUpdated(_) => Event::SpaceUpdated,
+                Payload::SpaceDeleted(_) => Event::SpaceDeleted,
+                Payload::GoalCreated(_) => Event::GoalCreated,
+                Payload::GoalUpdated(_) => Event::GoalUpdated,
+                Payload::GoalDeleted(_) => Event::GoalDeleted,
+                Payload::KeyResultCreated(_) => Event::KeyResultCreated,
+                Payload::KeyResultUpdated(_) => Event::KeyResultUpdated,
+                Payload::KeyResultDeleted(_) => Event::KeyResultDeleted,
+            }
+        }
+
+        pub fn webhook_id(&self) -> &str {
+            match self {
+                Payload::TaskCreated(p)
+                | Payload::TaskUpdated(p)
+                | Payload::TaskDeleted(p)
+                | Payload::TaskPriorityUpdated(p)
+                | Payload::TaskStatusUpdated(p)
+                | Payload::TaskAssigneeUpdated(p)
+                | Payload::TaskDueDateUpdated(p)
+                | Payload::TaskTagUpdated(p)
+                | Payload::TaskMoved(p)
+                | Payload::TaskCommentPosted(p)
+                | Payload::TaskCommentUpdated(p)
+                | Payload::TaskTimeEstimateUpdated(p)
+                | Payload::TaskTimeTrackedUpdated(p) => &p.webhook_id,
+                Payload::ListCreated(p) | Payload::ListUpdated(p) | Payload::ListDeleted(p) => {
+                    &p.webhook_id
+                }
+                Payload::FolderCreated(p)
+                | Payload::FolderUpdated(p)
+                | Payload::FolderDeleted(p) => &p.webhook_id,
+                Payload::SpaceCreated(p) | Payload::SpaceUpdated(p) | Payload::SpaceDeleted(p) => {
+                    &p.webhook_id
+                }
+                Payload::GoalCreated(p) | Payload::GoalUpdated(p) | Payload::GoalDeleted(p) => {
+                    &p.webhook_id
+                }
+                Payload::KeyResultCreated(p)
+                | Payload::KeyResultUpdated(p)
+                | Payload::KeyResultDeleted(p) => &p.webhook_id,
+            }
+        }
+
+        /// The task the event is about, `None` for list, folder, space and goal events.
+        pub fn task_id(&self) -> Option<&TaskId> {
+            match self {
+                Payload::TaskCreated(p)
+                | Payload::TaskUpdated(p)
+                | Payload::TaskDeleted(p)
+                | Payload::TaskPriorityUpdated(p)
+                | Payload::TaskStatusUpdated(p)
+                | Payload::TaskAssigneeUpdated(p)
+                | Payload::TaskDueDateUpdated(p)
+                | Payload::TaskTagUpdated(p)
+                | Payload::TaskMoved(p)
+                | Payload::TaskCommentPosted(p)
+                | Payload::TaskCommentUpdated(p)
+                | Payload::TaskTimeEstimateUpdated(p)
+                | Payload::TaskTimeTrackedUpdated(p) => Some(&p.task_id),
+                _ => None,
+            }
+        }
+
+        pub fn history_items(&self) -> &[HistoryItem] {
+            match self {
+                Payload::TaskCreated(p)
+                | Payload::TaskUpdated(p)
+                | Payload::TaskDeleted(p)
+                | Payload::TaskPriorityUpdated(p)
+                | Payload::TaskStatusUpdated(p)
+                | Payload::TaskAssigneeUpdated(p)
+                | Payload::TaskDueDateUpdated(p)
+                | Payload::TaskTagUpdated(p)
+                | Payload::TaskMoved(p)
+                | Payload::TaskCommentPosted(p)
+                | Payload::TaskCommentUpdated(p)
+                | Payload::TaskTimeEstimateUpdated(p)
+                | Payload::TaskTimeTrackedUpdated(p) => &p.history_items,
+                Payload::ListCreated(p) | Payload::ListUpdated(p) | Payload::ListDeleted(p) => {
+                    &p.history_items
+                }
+                Payload::FolderCreated(p)
+                | Payload::FolderUpdated(p)
+                | Payload::FolderDeleted(p) => &p.history_items,
+                Payload::SpaceCreated(p) | Payload::SpaceUpdated(p) | Payload::SpaceDeleted(p) => {
+                    &p.history_items
+                }
+                Payload::GoalCreated(p) | Payload::GoalUpdated(p) | Payload::GoalDeleted(p) => {
+                    &p.history_items
+                }
+                Payload::KeyResultCreated(p)
+                | Payload::KeyResultUpdated(p)
+                | Payload::KeyResultDeleted(p) => &p.history_items,
+            }
+        }
+
+        /// Whether every change of the event is one of the recent `writes` clicky made itself,
+        /// using those writes up.
+        pub fn is_own_change(&self, writes: &OwnWrites) -> bool {
+            let Some(task_id) = self.task_id() else {
+                return false;
+            };
+            writes.take(
+                task_id,
+                self.history_items().iter().map(|item| item.field.as_str()),
+            )
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_custom_field_change() {
+            let payload: Payload = serde_json::from_value(serde_json::json!({
+                "event": "taskUpdated",
+                "history_items": [{
+                    "id": "2800787904863010302",
+                    "type": 1,
+                    "date": "1642735012018",
+                    "field": "custom_field",
+                    "parent_id": "188335476",
+                    "data": {},
+                    "source": null,
+                    "user": {
+                        "id": 38221385,
+                        "username": "Karel",
+                        "email": "karel@example.com",
+                        "color": "#7b68ee",
+                        "initials": "K",
+                        "profilePicture": null
+                    },
+                    "before": "b2",
+                    "after": "c3",
+                    "custom_field": {
+                        "id": "5dc86497-098d-4bb0-87d6-cf28e43812e7",
+                        "name": "Milestone",
+                        "type": "drop_down",
+                        "type_config": {
+                            "options": [
+                                { "id": "b2", "name": "v0", "color": null, "orderindex": 1 },
+                                { "id": "c3", "name": "v1", "color": null, "orderindex": 2 }
+                            ]
+                        }
+                    }
+                }],
+                "task_id": "36w7wbr",
+                "webhook_id": "7fa3ec74-69a8-4530-a251-8a13730bd204"
+            }))
+            .unwrap();
+
+            assert_eq!(payload.event(), Event::TaskUpdated);
+            assert_eq!(payload.webhook_id(), "7fa3ec74-69a8-4530-a251-8a13730bd204");
+            assert_eq!(payload.task_id(), Some(&TaskId::from("36w7wbr")));
+
+            let item = &payload.history_items()[0];
+            assert_eq!(item.field, "custom_field");
+            assert_eq!(item.date.timestamp_millis(), 1642735012018);
+            assert_eq!(item.user.as_ref().unwrap().id, 38221385.into());
+            assert_eq!(item.after, "c3");
+            assert_eq!(item.custom_field.as_ref().unwrap().name, "Milestone");
+        }
+
+        #[test]
+        fn parses_list_event_without_history() {
+            let payload: Payload = serde_json::from_value(serde_json::json!({
+                "event": "listCreated",
+                "list_id": "188335750",
+                "webhook_id": "7fa3ec74-69a8-4530-a251-8a13730bd204"
+            }))
             .unwrap();
+
+            assert_eq!(payload.event(), Event::ListCreated);
+            assert_eq!(payload.task_id(), None);
+            assert!(payload.history_items().is_empty());
+            assert!(!payload.is_own_change(&OwnWrites::default()));
+        }
+
+        #[test]
+        fn recognises_own_changes_by_field() {
+            let item = |field: &str| HistoryItem {
+                field: field.into(),
+                // The same user makes changes by hand with the token clicky uses.
+                user: Some(User {
+                    id: 38221385.into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let payload = |items| {
+                Payload::TaskUpdated(TaskPayload {
+                    task_id: TaskId::from("36w7wbr"),
+                    history_items: items,
+                    ..Default::default()
+                })
+            };
+
+            let writes = OwnWrites::default();
+            writes.record(&TaskId::from("36w7wbr"), "parent");
+
+            assert!(!payload(vec![item("parent"), item("status")]).is_own_change(&writes));
+            assert!(payload(vec![item("parent")]).is_own_change(&writes));
+            assert!(!payload(vec![item("parent")]).is_own_change(&writes));
         }
     }
 }
@@ -167,9 +914,39 @@ pub mod events {
         }
     }
 
-    impl ToString for Event {
-        fn to_string(&self) -> String {
-            self.as_ref().to_string()
+    impl std::str::FromStr for Event {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Event::all()
+                .into_iter()
+                .find(|event| event.as_ref() == s)
+                .ok_or_else(|| format!("unknown event {s}"))
+        }
+    }
+
+    /// Deserializes the events of a webhook, where ClickUp uses `"*"` for all events.
+    ///
+    /// Events clicky does not know about are skipped rather than failing the whole webhook.
+    pub fn deserialize_events<'de, D>(deserializer: D) -> Result<Vec<Event>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let events = <Vec<String> as serde::Deserialize>::deserialize(deserializer)?;
+
+        if events.iter().any(|event| event == "*") {
+            return Ok(Event::all().into_iter().collect());
+        }
+
+        Ok(events
+            .iter()
+            .filter_map(|event| event.parse().ok())
+            .collect())
+    }
+
+    impl std::fmt::Display for Event {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.as_ref())
         }
     }
 
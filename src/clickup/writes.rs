@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::task::TaskId;
+
+/// How long a write is remembered, enough for ClickUp to deliver the events it causes.
+const TTL: Duration = Duration::from_secs(120);
+
+/// The changes clicky recently made to tasks, by task and the `field` of the history items
+/// ClickUp reports them with.
+///
+/// Webhook events for these changes are clicky's own and must not be acted on again. The same
+/// user may also make changes by hand with the same token, so events are told apart by what
+/// changed rather than by who changed it. Every recorded write matches one history item.
+#[derive(Debug)]
+pub struct OwnWrites {
+    ttl: Duration,
+    entries: Mutex<HashMap<(TaskId, String), Vec<Instant>>>,
+}
+
+impl Default for OwnWrites {
+    fn default() -> Self {
+        Self::new(TTL)
+    }
+}
+
+impl OwnWrites {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub fn record(&self, task: &TaskId, field: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, writes| {
+            writes.retain(|written| written.elapsed() < self.ttl);
+            !writes.is_empty()
+        });
+        entries
+            .entry((task.clone(), field.to_owned()))
+            .or_default()
+            .push(Instant::now());
+    }
+
+    /// Whether every one of `fields` changed on `task` is a recorded write, in which case those
+    /// writes are used up. Nothing is used up otherwise, and no fields are never a write.
+    pub fn take<'a>(&self, task: &TaskId, fields: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut wanted: HashMap<&str, usize> = HashMap::new();
+        for field in fields {
+            *wanted.entry(field).or_default() += 1;
+        }
+        if wanted.is_empty() {
+            return false;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let key = |field: &str| (task.clone(), field.to_owned());
+
+        let all_recorded = wanted.iter().all(|(&field, &count)| {
+            entries.get(&key(field)).is_some_and(|writes| {
+                writes
+                    .iter()
+                    .filter(|written| written.elapsed() < self.ttl)
+                    .count()
+                    >= count
+            })
+        });
+        if !all_recorded {
+            return false;
+        }
+
+        for (field, count) in wanted {
+            if let Some(writes) = entries.get_mut(&key(field)) {
+                writes.retain(|written| written.elapsed() < self.ttl);
+                writes.drain(..count);
+                if writes.is_empty() {
+                    entries.remove(&key(field));
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_each_write_once() {
+        let writes = OwnWrites::default();
+        let task = TaskId::from("36w7wbr");
+        writes.record(&task, "parent");
+        writes.record(&task, "comment");
+
+        assert!(!writes.take(&task, ["parent", "status"]));
+        assert!(!writes.take(&TaskId::from("36w83z6"), ["parent"]));
+        assert!(!writes.take(&task, []));
+
+        assert!(writes.take(&task, ["parent", "comment"]));
+        // The same change made by hand afterwards is not clicky's.
+        assert!(!writes.take(&task, ["parent"]));
+    }
+
+    #[test]
+    fn forgets_writes_after_ttl() {
+        let writes = OwnWrites::new(Duration::from_millis(10));
+        let task = TaskId::from("36w7wbr");
+        writes.record(&task, "parent");
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(!writes.take(&task, ["parent"]));
+    }
+}
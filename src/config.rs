@@ -1,38 +1,627 @@
-// use std::collections::HashMap;
-
-// use crate::clickup::{self, team::TeamId, webhooks::request::CreateWebhookParameters};
-
-// pub struct Config {
-//     credentials: Vec<Credential>,
-//     webhooks: Vec<Webhook>,
-// }
-
-// pub enum Credential {
-//     Authorization { name: String, token: String },
-// }
-
-// #[derive(Hash)]
-// pub enum Webhook {
-//     ClickUp {
-//         team_id: TeamId,
-//         request: CreateWebhookParameters,
-//         credentials: String,
-//     },
-// }
-// impl Webhook {
-//     async fn create(&self, cred: &Credential) -> color_eyre::eyre::Result<String> {
-//         match self {
-//             Self::ClickUp {
-//                 team_id, request, ..
-//             } => {
-//                 let token = match cred {
-//                     Credential::Authorization { name, token } => token,
-//                 };
-//                 clickup::webhooks::request::create_webhook(&token.into(), *team_id, request.clone())
-//                     .await
-//                     .map_err(|e| e.into())
-//                     .into()
-//             }
-//         }
-//     }
-// }
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{ensure, eyre, Result, WrapErr};
+use enumset::EnumSet;
+use serde::Deserialize;
+
+use crate::clickup::{
+    auth::{ClickupToken, OAuthApp, TokenStore},
+    client::{ClickupClient, ClickupConnection, ClientConfig},
+    custom_field::{CustomField, CustomFieldKind, DropdownOption},
+    list::ListId,
+    space::SpaceId,
+    task::TaskId,
+    team::TeamId,
+    webhooks::events::Event,
+};
+use crate::events::QueueConfig;
+use crate::secret::Secret;
+
+/// Path of the configuration file when `CLICKY_CONFIG` is not set.
+pub const DEFAULT_CONFIG_PATH: &str = "clicky.toml";
+
+/// Runtime configuration, loaded once at startup.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Address the HTTP server binds to.
+    #[serde(default = "default_listen")]
+    pub listen: SocketAddr,
+    /// Directory for state that must survive restarts, such as OAuth tokens.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// The OAuth app through which clicky is installed in other workspaces.
+    pub oauth: Option<OAuthApp>,
+    /// Bearer token for the `/admin` endpoints, which are disabled when it is not set.
+    pub admin_token: Option<Secret>,
+    #[serde(default)]
+    pub queue: QueueConfig,
+    #[serde(default)]
+    pub clickup: ClientConfig,
+    pub credentials: Vec<Credential>,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// All spaces for which milestone management is enabled
+    #[serde(default)]
+    pub spaces: Vec<Space>,
+}
+
+/// A named ClickUp API token, referenced by name from webhooks and spaces.
+///
+/// Either a personal `token`, which is a [`Secret`](crate::secret::Secret) so it can be read from
+/// the environment or a file, or the `team_id` of a workspace that installed clicky through OAuth.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credential {
+    pub name: String,
+    pub token: Option<ClickupToken>,
+    pub team_id: Option<TeamId>,
+}
+
+impl Credential {
+    /// The token to use, `None` when the OAuth workspace has not installed clicky yet.
+    pub fn token(&self, tokens: &TokenStore) -> Option<ClickupToken> {
+        match (&self.token, self.team_id) {
+            (Some(token), _) => Some(token.clone()),
+            (None, Some(team)) => tokens.get(team),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A ClickUp webhook registered on startup.
+///
+/// ClickUp delivers events for it to `endpoint`, whose last path segment must be `name`
+/// so incoming requests can be matched back to this entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub name: String,
+    pub team_id: TeamId,
+    pub endpoint: String,
+    pub credentials: String,
+    /// Events to subscribe to, all events when omitted.
+    pub events: Option<Vec<Event>>,
+}
+
+impl Webhook {
+    pub fn events(&self) -> EnumSet<Event> {
+        match &self.events {
+            Some(events) => events.iter().copied().collect(),
+            None => Event::all(),
+        }
+    }
+}
+
+/// A space with milestone management enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Space {
+    pub id: SpaceId,
+    /// The list holding the milestone tasks of this space.
+    pub milestone_list: ListId,
+    pub credentials: String,
+    /// Name or id of the `drop_down` custom field that selects a task's milestone.
+    #[serde(default = "default_milestone_field")]
+    pub milestone_field: String,
+    /// Milestone task for tasks whose milestone field is unset or maps to no milestone.
+    pub default_milestone: Option<TaskId>,
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+    /// Leave a comment on tasks that were moved under a milestone, explaining the move.
+    #[serde(default = "default_comment_on_move")]
+    pub comment_on_move: bool,
+}
+
+/// Maps an option of the milestone field to the milestone task its tasks are moved under.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Milestone {
+    /// Id or name of the dropdown option.
+    pub option: String,
+    pub task: TaskId,
+}
+
+impl Space {
+    pub fn is_milestone_field(&self, field: &CustomField) -> bool {
+        field.id == self.milestone_field || field.name == self.milestone_field
+    }
+
+    /// The milestone task for a selected option, matching on the option's id before its name.
+    pub fn milestone_task(&self, option: &DropdownOption) -> Option<&TaskId> {
+        self.milestones
+            .iter()
+            .find(|milestone| milestone.option == option.id)
+            .or_else(|| {
+                self.milestones
+                    .iter()
+                    .find(|milestone| milestone.option == option.name)
+            })
+            .map(|milestone| &milestone.task)
+    }
+
+    /// Every milestone task of the space, the default one first.
+    pub fn milestone_tasks(&self) -> Vec<&TaskId> {
+        let mut tasks: Vec<&TaskId> = self.default_milestone.iter().collect();
+        for milestone in &self.milestones {
+            if !tasks.contains(&&milestone.task) {
+                tasks.push(&milestone.task);
+            }
+        }
+        tasks
+    }
+
+    /// Checks that the space exists and that its milestone list is one of its lists.
+    pub async fn verify_exists(&self, client: &ClickupClient) -> Result<()> {
+        client
+            .get_space(&self.id)
+            .await
+            .wrap_err_with(|| format!("failed to fetch space `{}`", self.id))?;
+
+        let list = client
+            .get_list(&self.milestone_list)
+            .await
+            .wrap_err_with(|| {
+                format!(
+                    "failed to fetch milestone list {} of space `{}`",
+                    self.milestone_list, self.id
+                )
+            })?;
+        ensure!(
+            list.space.id == self.id,
+            "milestone list {} of space `{}` is in space `{}`",
+            self.milestone_list,
+            self.id,
+            list.space.id
+        );
+
+        Ok(())
+    }
+
+    /// Checks the milestone mapping against the field's options as configured in ClickUp.
+    pub async fn verify_milestones(&self, client: &ClickupClient) -> Result<()> {
+        let fields = client
+            .get_accessible_custom_fields(&self.milestone_list)
+            .await
+            .wrap_err_with(|| {
+                format!(
+                    "failed to fetch custom fields of milestone list {} in space `{}`",
+                    self.milestone_list.0, self.id
+                )
+            })?;
+
+        let field = fields
+            .iter()
+            .find(|field| self.is_milestone_field(field))
+            .ok_or_else(|| {
+                eyre!(
+                    "space `{}` has no custom field `{}` on milestone list {}",
+                    self.id,
+                    self.milestone_field,
+                    self.milestone_list.0
+                )
+            })?;
+        let CustomFieldKind::DropDown { options, .. } = &field.kind else {
+            return Err(eyre!(
+                "milestone field `{}` of space `{}` is a {} field, expected drop_down",
+                self.milestone_field,
+                self.id,
+                field.field_type()
+            ));
+        };
+
+        for milestone in &self.milestones {
+            ensure!(
+                options
+                    .iter()
+                    .any(|option| option.id == milestone.option || option.name == milestone.option),
+                "space `{}` maps unknown option `{}` of milestone field `{}`, available options: {}",
+                self.id,
+                milestone.option,
+                self.milestone_field,
+                options
+                    .iter()
+                    .map(|option| format!("{} ({})", option.name, option.id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        for option in options {
+            if self.milestone_task(option).is_none() {
+                tracing::warn!(
+                    "option `{}` of milestone field `{}` in space `{}` is not mapped to a milestone",
+                    option.name,
+                    self.milestone_field,
+                    self.id
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn default_listen() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 8080))
+}
+
+fn default_data_dir() -> PathBuf {
+    "data".into()
+}
+
+fn default_milestone_field() -> String {
+    "Milestone".into()
+}
+
+fn default_comment_on_move() -> bool {
+    true
+}
+
+impl Config {
+    /// Loads the configuration from `CLICKY_CONFIG`, falling back to [`DEFAULT_CONFIG_PATH`].
+    pub fn from_env() -> Result<Self> {
+        let path = std::env::var("CLICKY_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.into());
+        Self::load(path)
+    }
+
+    /// Reads, parses and validates the configuration file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read config file {}", path.display()))?;
+        contents
+            .parse()
+            .wrap_err_with(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Checks the cross references and values serde cannot check on its own.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.queue.workers > 0, "queue.workers must be at least 1");
+        ensure!(
+            self.queue.capacity >= self.queue.workers,
+            "queue.capacity must be at least queue.workers"
+        );
+        ensure!(
+            self.queue.max_attempts > 0,
+            "queue.max_attempts must be at least 1"
+        );
+
+        let base_url = reqwest::Url::parse(&self.clickup.base_url)
+            .wrap_err("clickup.base_url is not a valid url")?;
+        ensure!(
+            matches!(base_url.scheme(), "http" | "https"),
+            "clickup.base_url must be an http(s) url"
+        );
+
+        ensure!(
+            !self.credentials.is_empty(),
+            "at least one [[credentials]] entry is required"
+        );
+
+        let mut credentials = HashSet::new();
+        for credential in &self.credentials {
+            ensure!(
+                !credential.name.is_empty(),
+                "credentials must have a non-empty name"
+            );
+            ensure!(
+                credentials.insert(credential.name.as_str()),
+                "duplicate credentials `{}`",
+                credential.name
+            );
+            ensure!(
+                credential.token.is_some() != credential.team_id.is_some(),
+                "credentials `{}` need exactly one of `token` or `team_id`",
+                credential.name
+            );
+            ensure!(
+                credential.team_id.is_none() || self.oauth.is_some(),
+                "credentials `{}` use an OAuth installation but [oauth] is not configured",
+                credential.name
+            );
+        }
+
+        let mut webhooks = HashSet::new();
+        for webhook in &self.webhooks {
+            ensure!(
+                webhooks.insert(webhook.name.as_str()),
+                "duplicate webhook `{}`",
+                webhook.name
+            );
+            ensure!(
+                credentials.contains(webhook.credentials.as_str()),
+                "webhook `{}` references unknown credentials `{}`",
+                webhook.name,
+                webhook.credentials
+            );
+            let endpoint = reqwest::Url::parse(&webhook.endpoint)
+                .wrap_err_with(|| format!("webhook `{}` has an invalid endpoint", webhook.name))?;
+            ensure!(
+                matches!(endpoint.scheme(), "http" | "https"),
+                "webhook `{}` endpoint must be an http(s) url",
+                webhook.name
+            );
+            ensure!(
+                endpoint.path_segments().and_then(Iterator::last) == Some(webhook.name.as_str()),
+                "webhook `{}` endpoint {} must end in /webhook/{}",
+                webhook.name,
+                webhook.endpoint,
+                webhook.name
+            );
+            ensure!(
+                webhook
+                    .events
+                    .as_ref()
+                    .is_none_or(|events| !events.is_empty()),
+                "webhook `{}` subscribes to no events, omit `events` to subscribe to all",
+                webhook.name
+            );
+        }
+
+        let mut spaces = HashSet::new();
+        for space in &self.spaces {
+            ensure!(!space.id.0.is_empty(), "spaces must have a non-empty id");
+            ensure!(spaces.insert(&space.id), "duplicate space `{}`", space.id);
+            ensure!(
+                !space.milestone_list.0.is_empty(),
+                "space `{}` has an empty milestone_list",
+                space.id
+            );
+            ensure!(
+                credentials.contains(space.credentials.as_str()),
+                "space `{}` references unknown credentials `{}`",
+                space.id,
+                space.credentials
+            );
+
+            let mut options = HashSet::new();
+            for milestone in &space.milestones {
+                ensure!(
+                    options.insert(milestone.option.as_str()),
+                    "space `{}` maps milestone option `{}` more than once",
+                    space.id,
+                    milestone.option
+                );
+                ensure!(
+                    !milestone.task.0.is_empty(),
+                    "space `{}` maps milestone option `{}` to an empty task id",
+                    space.id,
+                    milestone.option
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the configured spaces against ClickUp, which requires their credentials to be valid.
+    ///
+    /// Spaces of workspaces that have not installed clicky through OAuth yet are skipped.
+    pub async fn verify(&self, connection: &ClickupConnection, tokens: &TokenStore) -> Result<()> {
+        for space in &self.spaces {
+            let Some(token) = self.token(&space.credentials, tokens) else {
+                tracing::warn!(
+                    "skipping verification of space `{}`, credentials `{}` are not installed yet",
+                    space.id,
+                    space.credentials
+                );
+                continue;
+            };
+            let client = connection.client(token);
+            space.verify_exists(&client).await?;
+            space.verify_milestones(&client).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn credential(&self, name: &str) -> Option<&Credential> {
+        self.credentials
+            .iter()
+            .find(|credential| credential.name == name)
+    }
+
+    pub fn token(&self, credentials: &str, tokens: &TokenStore) -> Option<ClickupToken> {
+        self.credential(credentials)?.token(tokens)
+    }
+
+    pub fn webhook(&self, name: &str) -> Option<&Webhook> {
+        self.webhooks.iter().find(|webhook| webhook.name == name)
+    }
+
+    pub fn space(&self, id: &SpaceId) -> Option<&Space> {
+        self.spaces.iter().find(|space| &space.id == id)
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Config = toml::from_str(s).map_err(|e| eyre!(e))?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// The configuration in the repository root, used by the tests that talk to ClickUp.
+///
+/// Like the deployment it reads the token from `CLICKUP_TOKEN`. Those tests are ignored by
+/// default and run with `CLICKUP_TOKEN=... cargo test -- --ignored`.
+#[cfg(test)]
+pub(crate) fn test_config() -> &'static Config {
+    static CONFIG: std::sync::OnceLock<Config> = std::sync::OnceLock::new();
+    CONFIG.get_or_init(|| {
+        Config::load(Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONFIG_PATH))
+            .expect("the repository config should be valid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = r#"
+        [[credentials]]
+        name = "clickup"
+        token = "pk_test"
+
+        [[webhooks]]
+        name = "clickup_id"
+        team_id = 20131398
+        endpoint = "https://clickity.fly.dev/webhook/clickup_id"
+        credentials = "clickup"
+
+        [[spaces]]
+        id = "32279886"
+        milestone_list = "188335476"
+        credentials = "clickup"
+        default_milestone = "36w8251"
+
+        [[spaces.milestones]]
+        option = "v0"
+        task = "36pnwzu"
+
+        [[spaces.milestones]]
+        option = "4a0d1b4e-1f0e-4a44-8a8e-0d6c1c6d3b1f"
+        task = "36w74wp"
+    "#;
+
+    fn option(id: &str, name: &str) -> DropdownOption {
+        DropdownOption {
+            id: id.into(),
+            name: name.into(),
+            color: None,
+            orderindex: 0,
+        }
+    }
+
+    fn error(config: &str) -> String {
+        config.parse::<Config>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_valid_config() {
+        let config: Config = VALID.parse().unwrap();
+
+        assert_eq!(config.listen, default_listen());
+        assert!(config.credential("clickup").is_some());
+        assert_eq!(config.webhook("clickup_id").unwrap().events(), Event::all());
+        assert_eq!(
+            config
+                .space(&SpaceId::from("32279886"))
+                .unwrap()
+                .milestone_list,
+            ListId::from("188335476")
+        );
+    }
+
+    #[test]
+    fn maps_milestone_options_by_id_or_name() {
+        let config: Config = VALID.parse().unwrap();
+        let space = config.space(&SpaceId::from("32279886")).unwrap();
+
+        assert_eq!(
+            space.milestone_task(&option("some-uuid", "v0")),
+            Some(&TaskId::from("36pnwzu"))
+        );
+        assert_eq!(
+            space.milestone_task(&option("4a0d1b4e-1f0e-4a44-8a8e-0d6c1c6d3b1f", "v1")),
+            Some(&TaskId::from("36w74wp"))
+        );
+        assert_eq!(space.milestone_task(&option("other-uuid", "v4")), None);
+    }
+
+    #[test]
+    fn rejects_duplicate_milestone_options() {
+        let config = VALID.replace(
+            "option = \"4a0d1b4e-1f0e-4a44-8a8e-0d6c1c6d3b1f\"",
+            "option = \"v0\"",
+        );
+
+        assert_eq!(
+            error(&config),
+            "space `32279886` maps milestone option `v0` more than once"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_credentials() {
+        let config = VALID.replace(
+            "milestone_list = \"188335476\"\n        credentials = \"clickup\"",
+            "milestone_list = \"188335476\"\n        credentials = \"other\"",
+        );
+
+        assert_eq!(
+            error(&config),
+            "space `32279886` references unknown credentials `other`"
+        );
+    }
+
+    #[test]
+    fn rejects_endpoint_not_ending_in_name() {
+        let config = VALID.replace("webhook/clickup_id", "webhook/other");
+
+        assert_eq!(
+            error(&config),
+            "webhook `clickup_id` endpoint https://clickity.fly.dev/webhook/other must end in /webhook/clickup_id"
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_spaces() {
+        let space = &VALID[VALID.find("[[spaces]]").unwrap()..];
+        let config = format!("{VALID}{space}");
+
+        assert_eq!(error(&config), "duplicate space `32279886`");
+    }
+
+    #[test]
+    fn reports_missing_token() {
+        let config = VALID.replace(
+            "token = \"pk_test\"",
+            "token = { env = \"CLICKY_TEST_TOKEN_UNSET\" }",
+        );
+
+        assert!(error(&config)
+            .contains("failed to read secret from environment variable CLICKY_TEST_TOKEN_UNSET"));
+    }
+
+    #[test]
+    fn token_is_redacted() {
+        let config: Config = VALID.parse().unwrap();
+
+        assert!(!format!("{config:?}").contains("pk_test"));
+    }
+
+    #[test]
+    fn oauth_credentials_require_oauth_app() {
+        let config = VALID.replace("token = \"pk_test\"", "team_id = \"20131398\"");
+        assert_eq!(
+            error(&config),
+            "credentials `clickup` use an OAuth installation but [oauth] is not configured"
+        );
+
+        let config = format!(
+            "{}\n[oauth]\nclient_id = \"client\"\nclient_secret = \"secret\"\nredirect_uri = \"https://clickity.fly.dev/oauth/callback\"\n",
+            config
+        );
+        let config: Config = config.parse().unwrap();
+        assert_eq!(
+            config.credential("clickup").unwrap().team_id,
+            Some(TeamId(20131398))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let config = format!("team_id = 1\n{VALID}");
+
+        assert!(error(&config).contains("unknown field `team_id`"));
+    }
+}
@@ -1 +1,499 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    future::Future,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::clickup::error::ClickupError;
+use crate::clickup::webhooks::{events::Event, payload::Payload};
+use crate::server::AppState;
+
+pub mod store;
+
+use store::{EventId, EventStatus, StoredEvent};
+
+/// Sizing of the queue between the webhook endpoint and the workers processing its events.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct QueueConfig {
+    /// Number of events processed concurrently.
+    pub workers: usize,
+    /// Events waiting to be processed before deliveries are turned away.
+    pub capacity: usize,
+    /// Attempts at processing an event before it is dead-lettered.
+    pub max_attempts: u32,
+    /// Delay before the first retry of a failed event, doubled for every further retry.
+    pub retry_delay_secs: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            capacity: 1024,
+            max_attempts: 5,
+            retry_delay_secs: 10,
+        }
+    }
+}
+
+impl QueueConfig {
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        Duration::from_secs(self.retry_delay_secs) * 2u32.saturating_pow(attempt - 1)
+    }
+}
+
+/// A verified webhook delivery waiting to be processed.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Id of the delivery in the [`store::EventStore`].
+    pub id: EventId,
+    /// Name of the configured webhook the event was delivered to.
+    pub webhook: String,
+    pub payload: Payload,
+}
+
+impl Job {
+    /// Parses a stored delivery back into a job.
+    pub fn from_stored(event: &StoredEvent) -> serde_json::Result<Self> {
+        Ok(Self {
+            id: event.id,
+            webhook: event.webhook.clone(),
+            payload: serde_json::from_str(&event.payload)?,
+        })
+    }
+
+    /// Events with the same key are processed in the order they were received.
+    fn key(&self) -> &str {
+        match self.payload.task_id() {
+            Some(task_id) => &task_id.0,
+            None => self.payload.webhook_id(),
+        }
+    }
+}
+
+/// Returned when the queue cannot take any more events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// A bounded queue feeding a fixed pool of workers.
+///
+/// Every worker owns a slice of the capacity, and events are assigned to workers by their
+/// [`Job::key`], so the events of a task are processed one at a time and in order while events of
+/// different tasks are processed concurrently. A failed job is tried again after its retry delay,
+/// and later jobs with its key wait behind it until it is finished, while the worker goes on
+/// with the jobs of other keys. Waiting jobs no longer count towards the capacity.
+///
+/// A job is in the queue once from being pushed until it is finished, pushing it again in the
+/// meantime, such as when replaying it, does nothing.
+#[derive(Debug, Clone)]
+pub struct Queue {
+    workers: Vec<mpsc::Sender<Job>>,
+    queued: Arc<Mutex<HashSet<EventId>>>,
+}
+
+impl Queue {
+    /// Spawns the workers, which call `process` for every job pushed onto the queue. `process`
+    /// returns the delay after which to process the job again, or `None` when it is finished.
+    pub fn start<F, Fut>(config: &QueueConfig, process: F) -> Self
+    where
+        F: Fn(Job) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = Option<Duration>> + Send + 'static,
+    {
+        let workers = config.workers.max(1);
+        let capacity = (config.capacity / workers).max(1);
+        let queued = Arc::new(Mutex::new(HashSet::new()));
+
+        let workers = (0..workers)
+            .map(|_| {
+                let (sender, receiver) = mpsc::channel::<Job>(capacity);
+                let worker = Worker {
+                    process: process.clone(),
+                    queued: queued.clone(),
+                    lanes: HashMap::new(),
+                };
+                tokio::task::spawn(worker.run(receiver));
+
+                sender
+            })
+            .collect();
+
+        Self { workers, queued }
+    }
+
+    fn worker(&self, job: &Job) -> &mpsc::Sender<Job> {
+        let mut hasher = DefaultHasher::new();
+        job.key().hash(&mut hasher);
+        &self.workers[(hasher.finish() % self.workers.len() as u64) as usize]
+    }
+
+    /// Marks the job as queued, returning `false` when it already is.
+    fn claim(&self, job: &Job) -> bool {
+        self.queued.lock().unwrap().insert(job.id)
+    }
+
+    fn release(&self, id: EventId) {
+        self.queued.lock().unwrap().remove(&id);
+    }
+
+    /// Enqueues a job without waiting, failing when its worker has no capacity left.
+    pub fn push(&self, job: Job) -> Result<(), QueueFull> {
+        if !self.claim(&job) {
+            return Ok(());
+        }
+
+        let id = job.id;
+        self.worker(&job).try_send(job).map_err(|_| {
+            self.release(id);
+            QueueFull
+        })
+    }
+
+    /// Enqueues a job, waiting for its worker to have capacity.
+    pub async fn push_wait(&self, job: Job) {
+        if !self.claim(&job) {
+            return;
+        }
+
+        let id = job.id;
+        if self.worker(&job).send(job).await.is_err() {
+            self.release(id);
+            tracing::error!("Event queue worker stopped");
+        }
+    }
+}
+
+/// Processes the jobs of the keys assigned to one worker of a [`Queue`].
+struct Worker<F> {
+    process: F,
+    queued: Arc<Mutex<HashSet<EventId>>>,
+    /// The jobs of the keys with a failed job waiting for its retry, which is first in line.
+    lanes: HashMap<String, VecDeque<Job>>,
+}
+
+impl<F, Fut> Worker<F>
+where
+    F: Fn(Job) -> Fut,
+    Fut: Future<Output = Option<Duration>>,
+{
+    async fn run(mut self, mut jobs: mpsc::Receiver<Job>) {
+        let (retry, mut retries) = mpsc::unbounded_channel::<String>();
+
+        loop {
+            let key = tokio::select! {
+                Some(job) = jobs.recv() => {
+                    let key = job.key().to_owned();
+                    let waiting = self.lanes.contains_key(&key);
+                    self.lanes.entry(key.clone()).or_default().push_back(job);
+                    if waiting {
+                        continue;
+                    }
+                    key
+                }
+                Some(key) = retries.recv() => key,
+                else => break,
+            };
+
+            if let Some(delay) = self.work_off(&key).await {
+                let retry = retry.clone();
+                tokio::task::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    // Only fails once the worker has stopped.
+                    let _ = retry.send(key);
+                });
+            }
+        }
+    }
+
+    /// Processes the jobs of `key` in order, returning the retry delay of the first one that
+    /// fails, which stays first in line.
+    async fn work_off(&mut self, key: &str) -> Option<Duration> {
+        while let Some(job) = self.lanes.get(key).and_then(|lane| lane.front()) {
+            if let Some(delay) = (self.process)(job.clone()).await {
+                return Some(delay);
+            }
+
+            if let Some(job) = self.lanes.get_mut(key).and_then(VecDeque::pop_front) {
+                self.queued.lock().unwrap().remove(&job.id);
+            }
+        }
+
+        self.lanes.remove(key);
+        None
+    }
+}
+
+/// Makes an attempt at processing a stored job, returning the delay before the next attempt when
+/// it failed. The job is dead-lettered once every attempt failed.
+pub async fn handle(state: &AppState, job: &Job) -> Option<Duration> {
+    let attempt = match state.events.start_attempt(job.id) {
+        Ok(attempt) => attempt,
+        Err(err) => {
+            tracing::error!("Error updating event {}: {:?}", job.id, err);
+            return None;
+        }
+    };
+
+    let (status, error) = match process(state, job).await {
+        Ok(()) => (EventStatus::Done, None),
+        Err(err) => {
+            tracing::error!(
+                "Error processing {} event {} (attempt {}): {:?}",
+                job.payload.event(),
+                job.id,
+                attempt,
+                err
+            );
+            if err.is_retryable() && attempt < state.config.queue.max_attempts {
+                (EventStatus::Failed, Some(err.to_string()))
+            } else {
+                (EventStatus::Dead, Some(err.to_string()))
+            }
+        }
+    };
+
+    if let Err(err) = state
+        .events
+        .finish_attempt(job.id, status, error.as_deref())
+    {
+        tracing::error!("Error updating event {}: {:?}", job.id, err);
+        return None;
+    }
+
+    match status {
+        EventStatus::Failed => Some(state.config.queue.retry_delay(attempt)),
+        EventStatus::Dead => {
+            tracing::warn!("Event {} is dead after {} attempts", job.id, attempt);
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Re-enqueues the events that were not finished when clicky last stopped.
+pub async fn recover(state: &AppState, queue: &Queue) -> rusqlite::Result<()> {
+    let events = state.events.unfinished()?;
+    if !events.is_empty() {
+        tracing::info!("Resuming {} unfinished events", events.len());
+    }
+
+    for event in events {
+        enqueue(state, queue, &event).await?;
+    }
+
+    Ok(())
+}
+
+/// Resets a stored event and enqueues it again, returning `false` when there is no such event.
+pub async fn replay(state: &AppState, queue: &Queue, id: EventId) -> rusqlite::Result<bool> {
+    if !state.events.reset(id)? {
+        return Ok(false);
+    }
+
+    match state.events.get(id)? {
+        Some(event) => enqueue(state, queue, &event).await.map(|()| true),
+        None => Ok(false),
+    }
+}
+
+async fn enqueue(state: &AppState, queue: &Queue, event: &StoredEvent) -> rusqlite::Result<()> {
+    match Job::from_stored(event) {
+        Ok(job) => queue.push_wait(job).await,
+        Err(err) => {
+            tracing::error!("Stored event {} is not a valid payload: {}", event.id, err);
+            state
+                .events
+                .finish_attempt(event.id, EventStatus::Dead, Some(&err.to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the automations for a webhook event.
+pub async fn process(state: &AppState, job: &Job) -> Result<(), ClickupError> {
+    use crate::clickup::actions::make_task_subtask_of_milestone_task_if_needed;
+
+    let Some(task_id) = job.payload.task_id() else {
+        tracing::debug!("Ignoring {} event", job.payload.event());
+        return Ok(());
+    };
+
+    if job.payload.event() == Event::TaskDeleted {
+        return Ok(());
+    }
+
+    let Some(client) = state
+        .config
+        .webhook(&job.webhook)
+        .and_then(|webhook| state.client(&webhook.credentials))
+    else {
+        tracing::error!("No credentials for webhook {}", job.webhook);
+        return Ok(());
+    };
+
+    // Moving a task triggers events of its own, which should not be acted on again.
+    if job.payload.is_own_change(client.connection().own_writes()) {
+        tracing::debug!("Ignoring {} event caused by clicky", job.payload.event());
+        return Ok(());
+    }
+
+    let task = client.get_task(task_id).await?;
+
+    make_task_subtask_of_milestone_task_if_needed(&client, &state.config, &task).await?;
+    tracing::info!("Successfully made task subtask of milestone {:?}", task.id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::clickup::{
+        task::TaskId,
+        webhooks::payload::{ListPayload, TaskPayload},
+    };
+
+    fn job(task_id: &str, id: i64) -> Job {
+        Job {
+            id: EventId(id),
+            webhook: "clickup_id".into(),
+            payload: Payload::TaskUpdated(TaskPayload {
+                webhook_id: "4b67ac88".into(),
+                task_id: TaskId::from(task_id),
+                history_items: vec![],
+            }),
+        }
+    }
+
+    /// Starts a queue reporting every attempt at a job, which `retry` decides the outcome of.
+    fn queue(
+        workers: usize,
+        retry: impl Fn(&Job) -> Option<Duration> + Clone + Send + 'static,
+    ) -> (Queue, mpsc::UnboundedReceiver<EventId>) {
+        let (attempts, receiver) = mpsc::unbounded_channel();
+        let queue = Queue::start(
+            &QueueConfig {
+                workers,
+                ..Default::default()
+            },
+            move |job: Job| {
+                let attempts = attempts.clone();
+                let retry = retry.clone();
+                async move {
+                    // Later jobs finish faster, so only the queue keeps them in order.
+                    tokio::time::sleep(Duration::from_millis(100 - job.id.0 as u64)).await;
+                    attempts.send(job.id).unwrap();
+                    retry(&job)
+                }
+            },
+        );
+        (queue, receiver)
+    }
+
+    async fn next(attempts: &mut mpsc::UnboundedReceiver<EventId>, count: usize) -> Vec<i64> {
+        let mut ids = Vec::new();
+        for _ in 0..count {
+            ids.push(attempts.recv().await.unwrap().0);
+        }
+        ids
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn processes_events_of_a_task_in_order() {
+        let (queue, mut attempts) = queue(4, |_| None);
+
+        for n in 0..10 {
+            queue.push(job("36w7wbr", 2 * n)).unwrap();
+            queue.push(job("36w83z6", 2 * n + 1)).unwrap();
+        }
+
+        let processed = next(&mut attempts, 20).await;
+        for parity in [0, 1] {
+            let order: Vec<_> = processed.iter().filter(|id| *id % 2 == parity).collect();
+            assert!(order.windows(2).all(|ids| ids[0] < ids[1]), "{order:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_failed_jobs_without_blocking_the_worker() {
+        let failed = Arc::new(Mutex::new(false));
+        let (queue, mut attempts) = queue(1, move |job| {
+            // The first job fails on its first attempt.
+            let first_attempt = !std::mem::replace(&mut *failed.lock().unwrap(), true);
+            (job.id == EventId(0) && first_attempt).then_some(Duration::from_secs(10))
+        });
+
+        queue.push(job("36w7wbr", 0)).unwrap();
+        queue.push(job("36w83z6", 1)).unwrap();
+
+        assert_eq!(next(&mut attempts, 3).await, [0, 1, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_later_events_of_a_task_behind_its_retry() {
+        let failed = Arc::new(Mutex::new(false));
+        let (queue, mut attempts) = queue(1, move |job| {
+            let first_attempt = !std::mem::replace(&mut *failed.lock().unwrap(), true);
+            (job.id == EventId(0) && first_attempt).then_some(Duration::from_secs(10))
+        });
+
+        queue.push(job("36w7wbr", 0)).unwrap();
+        assert_eq!(next(&mut attempts, 1).await, [0]);
+
+        // Queued while the first event waits for its retry.
+        queue.push(job("36w7wbr", 1)).unwrap();
+        queue.push(job("36w83z6", 2)).unwrap();
+
+        assert_eq!(next(&mut attempts, 3).await, [2, 0, 1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queues_a_job_once_until_it_is_finished() {
+        let (queue, mut attempts) = queue(1, |_| None);
+
+        // A replay racing the recovery of the same event.
+        queue.push(job("36w7wbr", 0)).unwrap();
+        queue.push_wait(job("36w7wbr", 0)).await;
+        queue.push(job("36w7wbr", 1)).unwrap();
+        assert_eq!(next(&mut attempts, 2).await, [0, 1]);
+
+        queue.push(job("36w7wbr", 0)).unwrap();
+        assert_eq!(next(&mut attempts, 1).await, [0]);
+    }
+
+    #[tokio::test]
+    async fn rejects_jobs_when_full() {
+        let queue = Queue::start(
+            &QueueConfig {
+                workers: 1,
+                capacity: 1,
+                ..Default::default()
+            },
+            |_: Job| std::future::pending::<Option<Duration>>(),
+        );
+
+        let list_job = |n: usize| Job {
+            id: EventId(n as i64),
+            webhook: "clickup_id".into(),
+            payload: Payload::ListCreated(ListPayload {
+                webhook_id: n.to_string(),
+                ..Default::default()
+            }),
+        };
+
+        // The first job is taken by the worker, the second waits in the queue.
+        queue.push(list_job(0)).unwrap();
+        tokio::task::yield_now().await;
+        queue.push(list_job(1)).unwrap();
+
+        assert_eq!(queue.push(list_job(2)), Err(QueueFull));
+    }
+}
@@ -0,0 +1,382 @@
+use std::{fmt, path::Path, str::FromStr, sync::Mutex};
+
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// Id of a received webhook delivery in the [`EventStore`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct EventId(pub i64);
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventStatus {
+    /// Received and waiting for a worker.
+    Pending,
+    Processing,
+    /// The last attempt failed, it is retried.
+    Failed,
+    Done,
+    /// Every attempt failed, it is only processed again when replayed.
+    Dead,
+    /// A delivery of changes that were already received, it is not processed.
+    Duplicate,
+}
+
+impl EventStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventStatus::Pending => "pending",
+            EventStatus::Processing => "processing",
+            EventStatus::Failed => "failed",
+            EventStatus::Done => "done",
+            EventStatus::Dead => "dead",
+            EventStatus::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl FromStr for EventStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(EventStatus::Pending),
+            "processing" => Ok(EventStatus::Processing),
+            "failed" => Ok(EventStatus::Failed),
+            "done" => Ok(EventStatus::Done),
+            "dead" => Ok(EventStatus::Dead),
+            "duplicate" => Ok(EventStatus::Duplicate),
+            _ => Err(format!("unknown event status {s}")),
+        }
+    }
+}
+
+/// A received webhook delivery and how far its processing got.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoredEvent {
+    pub id: EventId,
+    /// Name of the configured webhook it was delivered to.
+    pub webhook: String,
+    /// The raw JSON body.
+    pub payload: String,
+    pub status: EventStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub received_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoredEvent {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let status: String = row.get("status")?;
+        Ok(Self {
+            id: EventId(row.get("id")?),
+            webhook: row.get("webhook")?,
+            payload: row.get("payload")?,
+            status: status.parse().map_err(|_| {
+                rusqlite::Error::InvalidColumnType(3, "status".into(), rusqlite::types::Type::Text)
+            })?,
+            attempts: row.get("attempts")?,
+            last_error: row.get("last_error")?,
+            received_at: from_millis(row.get("received_at")?),
+            updated_at: from_millis(row.get("updated_at")?),
+        })
+    }
+}
+
+fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or_else(Utc::now)
+}
+
+/// Filter for [`EventStore::list`], fields that are `None` match every event.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    pub status: Option<EventStatus>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+/// Every received webhook delivery with its processing status, kept in SQLite so nothing is lost
+/// when clicky restarts or ClickUp fails.
+#[derive(Debug)]
+pub struct EventStore {
+    connection: Mutex<Connection>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        webhook TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        last_error TEXT,
+        received_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_status ON events (status);
+    CREATE INDEX IF NOT EXISTS events_received_at ON events (received_at);
+    CREATE TABLE IF NOT EXISTS history_items (
+        id TEXT PRIMARY KEY,
+        event_id INTEGER NOT NULL REFERENCES events (id)
+    );
+";
+
+impl EventStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|_| rusqlite::Error::InvalidPath(path.to_owned()))?;
+        }
+
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> rusqlite::Result<Self> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().expect("event store lock poisoned")
+    }
+
+    /// Stores a newly received delivery as pending.
+    pub fn insert(&self, webhook: &str, payload: &str) -> rusqlite::Result<EventId> {
+        let now = Utc::now().timestamp_millis();
+        let connection = self.connection();
+        connection.execute(
+            "INSERT INTO events (webhook, payload, status, received_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![webhook, payload, EventStatus::Pending.as_str(), now],
+        )?;
+
+        Ok(EventId(connection.last_insert_rowid()))
+    }
+
+    /// Records the history items delivered with an event, returning whether any of them is new.
+    ///
+    /// ClickUp can deliver the same changes more than once, and every change has a unique history
+    /// item id. Events without history items are always considered new.
+    pub fn record_history_items<'a>(
+        &self,
+        id: EventId,
+        items: impl IntoIterator<Item = &'a str>,
+    ) -> rusqlite::Result<bool> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        let mut any = false;
+        let mut new = false;
+        {
+            let mut statement = transaction
+                .prepare("INSERT OR IGNORE INTO history_items (id, event_id) VALUES (?1, ?2)")?;
+            for item in items {
+                any = true;
+                new |= statement.execute(params![item, id.0])? > 0;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(new || !any)
+    }
+
+    /// Removes an event that was never processed, for deliveries that were turned away.
+    pub fn discard(&self, id: EventId) -> rusqlite::Result<()> {
+        let connection = self.connection();
+        connection.execute(
+            "DELETE FROM history_items WHERE event_id = ?1",
+            params![id.0],
+        )?;
+        connection.execute("DELETE FROM events WHERE id = ?1", params![id.0])?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: EventId) -> rusqlite::Result<Option<StoredEvent>> {
+        self.connection()
+            .query_row(
+                "SELECT * FROM events WHERE id = ?1",
+                params![id.0],
+                StoredEvent::from_row,
+            )
+            .optional()
+    }
+
+    /// Marks an event as being processed, returning the number of the attempt.
+    pub fn start_attempt(&self, id: EventId) -> rusqlite::Result<u32> {
+        self.connection().query_row(
+            "UPDATE events SET status = ?2, attempts = attempts + 1, updated_at = ?3
+             WHERE id = ?1 RETURNING attempts",
+            params![
+                id.0,
+                EventStatus::Processing.as_str(),
+                Utc::now().timestamp_millis()
+            ],
+            |row| row.get(0),
+        )
+    }
+
+    /// Records the outcome of an attempt.
+    pub fn finish_attempt(
+        &self,
+        id: EventId,
+        status: EventStatus,
+        error: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        self.connection().execute(
+            "UPDATE events SET status = ?2, last_error = ?3, updated_at = ?4 WHERE id = ?1",
+            params![id.0, status.as_str(), error, Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    /// Resets an event to pending with a fresh set of attempts, so it can be processed again.
+    pub fn reset(&self, id: EventId) -> rusqlite::Result<bool> {
+        let updated = self.connection().execute(
+            "UPDATE events SET status = ?2, attempts = 0, updated_at = ?3 WHERE id = ?1",
+            params![
+                id.0,
+                EventStatus::Pending.as_str(),
+                Utc::now().timestamp_millis()
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Events that were received but not done or dead, oldest first, to resume after a restart.
+    pub fn unfinished(&self) -> rusqlite::Result<Vec<StoredEvent>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT * FROM events WHERE status IN ('pending', 'processing', 'failed') ORDER BY id",
+        )?;
+        let events = statement.query_map([], StoredEvent::from_row)?;
+        events.collect()
+    }
+
+    /// Events matching `filter`, oldest first.
+    pub fn list(&self, filter: &EventFilter) -> rusqlite::Result<Vec<StoredEvent>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT * FROM events
+             WHERE (?1 IS NULL OR status = ?1)
+               AND (?2 IS NULL OR received_at >= ?2)
+               AND (?3 IS NULL OR received_at <= ?3)
+             ORDER BY id
+             LIMIT ?4",
+        )?;
+        let events = statement.query_map(
+            params![
+                filter.status.map(|status| status.as_str()),
+                filter.from.map(|from| from.timestamp_millis()),
+                filter.to.map(|to| to.timestamp_millis()),
+                filter.limit.map_or(-1, i64::from),
+            ],
+            StoredEvent::from_row,
+        )?;
+        events.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_attempts_until_dead() {
+        let store = EventStore::in_memory().unwrap();
+        let id = store.insert("clickup_id", "{}").unwrap();
+
+        assert_eq!(store.start_attempt(id).unwrap(), 1);
+        store
+            .finish_attempt(id, EventStatus::Failed, Some("timeout"))
+            .unwrap();
+        assert_eq!(store.unfinished().unwrap().len(), 1);
+
+        assert_eq!(store.start_attempt(id).unwrap(), 2);
+        store
+            .finish_attempt(id, EventStatus::Dead, Some("timeout"))
+            .unwrap();
+        assert!(store.unfinished().unwrap().is_empty());
+
+        let event = store.get(id).unwrap().unwrap();
+        assert_eq!(event.status, EventStatus::Dead);
+        assert_eq!(event.attempts, 2);
+        assert_eq!(event.last_error.as_deref(), Some("timeout"));
+
+        assert!(store.reset(id).unwrap());
+        let event = store.get(id).unwrap().unwrap();
+        assert_eq!(event.status, EventStatus::Pending);
+        assert_eq!(event.attempts, 0);
+    }
+
+    #[test]
+    fn recognises_redelivered_history_items() {
+        let store = EventStore::in_memory().unwrap();
+        let first = store.insert("clickup_id", "{}").unwrap();
+        let second = store.insert("clickup_id", "{}").unwrap();
+        let third = store.insert("clickup_id", "{}").unwrap();
+
+        assert!(store.record_history_items(first, ["1", "2"]).unwrap());
+        assert!(!store.record_history_items(second, ["2", "1"]).unwrap());
+        assert!(store.record_history_items(third, ["2", "3"]).unwrap());
+        assert!(store.record_history_items(third, []).unwrap());
+
+        // A delivery turned away is new again when ClickUp retries it.
+        store.discard(third).unwrap();
+        let retried = store.insert("clickup_id", "{}").unwrap();
+        assert!(store.record_history_items(retried, ["3"]).unwrap());
+    }
+
+    #[test]
+    fn lists_by_status_and_time() {
+        let store = EventStore::in_memory().unwrap();
+        let first = store.insert("clickup_id", "{}").unwrap();
+        let second = store.insert("clickup_id", "{}").unwrap();
+        store
+            .finish_attempt(second, EventStatus::Dead, None)
+            .unwrap();
+
+        let dead = store
+            .list(&EventFilter {
+                status: Some(EventStatus::Dead),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(dead.iter().map(|e| e.id).collect::<Vec<_>>(), [second]);
+
+        let all = store.list(&EventFilter::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|e| e.id).collect::<Vec<_>>(),
+            [first, second]
+        );
+
+        let future = store
+            .list(&EventFilter {
+                from: Some(Utc::now() + chrono::Duration::hours(1)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(future.is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod clickup;
+pub mod config;
+pub mod events;
+pub mod github;
+pub mod secret;
+pub mod server;
+pub mod store;
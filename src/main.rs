@@ -1,103 +1,67 @@
-mod clickup;
-mod config;
-mod github;
+use clicky::{config::Config, server::AppState};
+use color_eyre::eyre::{bail, eyre, WrapErr};
 
-use axum::{
-    extract::Path,
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post},
-    Router,
-};
-use clickup::{auth::ClickupToken, task::TaskId};
-use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
-
-use crate::clickup::{list::ListId, team::TeamId};
-use uuid::Uuid;
-
-pub const TEAM_ID: TeamId = TeamId(20131398);
-pub const CLICKUP_WEBHOOK: &str = "https://clickity.fly.dev/webhook/clickup_id";
-pub const CLICKUP_TOKEN: ClickupToken =
-    ClickupToken("pk_38221385_ZO414SRT0JWLDX77FHFNLCJE0LRR9ELN");
-
-/// All spaces for which milestone management is enabled
-/// TODO: make nicer structure instead of &str slice
-pub const MILESTONE_SPACES: [&str; 1] = ["32279886"];
-/// The milestone list for each milestone space, matches by index
-/// TODO: make nicer structure instead of &str slice
-pub const MILESTONE_LISTS: [&str; 1] = ["188335476"];
+const USAGE: &str = "usage: clicky [replay <event id> | replay --from <RFC 3339> --to <RFC 3339>]";
 
 #[tokio::main]
-async fn main() {
+async fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
     tracing_subscriber::fmt::init();
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/create", get(create))
-        .route("/webhook/:webhook_id", post(webhook));
-
-    tokio::task::spawn(async {
-        use crate::clickup::webhooks::{events::Event, request};
-        let response =
-            request::create_webhook(&CLICKUP_TOKEN, TEAM_ID, (CLICKUP_WEBHOOK, Event::all()))
-                .await
-                .expect("creating a webhook should work");
-
-        tracing::info!("created webhook: {:?}", response)
-    });
+    let config = Config::from_env()?;
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
-    tracing::debug!("listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
-}
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("replay") => return replay(&config, &args[1..]).await,
+        Some(_) => bail!(USAGE),
+    }
 
-async fn root() -> &'static str {
-    "Hello, World!"
-}
+    let state = AppState::open(config)?;
+    state.config.verify(&state.clickup, &state.tokens).await?;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct Event {
-    pub task_id: TaskId,
+    clicky::server::serve(state).await
 }
 
-async fn webhook(Path(_): Path<String>, payload: bytes::Bytes) -> impl IntoResponse {
-    use clickup::actions::{get_task, make_task_subtask_of_milestone_task_if_needed};
-
-    let Ok(event) = serde_json::from_slice::<Event>(&payload) else {
-        tracing::error!("Invalid payload received");
-        return StatusCode::INTERNAL_SERVER_ERROR;
-    };
-
-    let Ok(task) = get_task(&CLICKUP_TOKEN, &event.task_id).await else {
-        tracing::error!("Error getting task from clickup");
-        return StatusCode::INTERNAL_SERVER_ERROR;
-    };
-
-    match make_task_subtask_of_milestone_task_if_needed(&CLICKUP_TOKEN, &task).await {
-        Ok(_) => {
-            tracing::info!("Successfully made task subtask of milestone {:?}", task);
-            StatusCode::OK
+/// Asks the running server to replay stored events through its `/admin/events` endpoints.
+async fn replay(config: &Config, args: &[String]) -> color_eyre::Result<()> {
+    let admin_token = config
+        .admin_token
+        .as_ref()
+        .ok_or_else(|| eyre!("replaying events requires admin_token in the config"))?;
+
+    let base = format!("http://127.0.0.1:{}/admin/events", config.listen.port());
+    let client = reqwest::Client::new();
+    let request = match args {
+        [id] => {
+            let id: i64 = id.parse().wrap_err("invalid event id")?;
+            client.post(format!("{base}/{id}/replay"))
         }
-        Err(err) => {
-            tracing::error!("Error making task subtask of milestone {:?}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
+        [flag_a, a, flag_b, b] => {
+            let (from, to) = match (flag_a.as_str(), flag_b.as_str()) {
+                ("--from", "--to") => (a, b),
+                ("--to", "--from") => (b, a),
+                _ => bail!(USAGE),
+            };
+            for timestamp in [from, to] {
+                chrono::DateTime::parse_from_rfc3339(timestamp)
+                    .wrap_err_with(|| format!("invalid timestamp {timestamp}"))?;
+            }
+            client
+                .post(format!("{base}/replay"))
+                .query(&[("from", from), ("to", to)])
         }
-    }
-}
-
-async fn create() -> String {
-    use crate::clickup::actions::create_task;
+        _ => bail!(USAGE),
+    };
 
-    let name = format!("Generated task {}", Uuid::new_v4());
-    let list = ListId::from("188335750");
-    let res = create_task(&CLICKUP_TOKEN, &list, &name).await;
+    let replayed: Vec<i64> = request
+        .bearer_auth(admin_token.expose())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
 
-    match res {
-        Ok(r) => format!("Task {name} created with res {r}"),
-        Err(e) => format!("Error creating task: {e}"),
-    }
+    tracing::info!("Replaying {} events: {:?}", replayed.len(), replayed);
+    Ok(())
 }
@@ -0,0 +1,122 @@
+use std::{fmt, path::PathBuf};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// A secret value such as an API token.
+///
+/// In the config a secret is either given inline, or read from an environment variable
+/// (`{ env = "CLICKUP_TOKEN" }`) or a file (`{ file = "/run/secrets/clickup" }`) when loading.
+/// Its `Debug` output is redacted so it can be logged as part of larger structures.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SecretSource")]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// Writes the secret itself, for the stores that persist secrets clicky was handed.
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SecretSource {
+    Inline(String),
+    Env { env: String },
+    File { file: PathBuf },
+}
+
+impl TryFrom<SecretSource> for Secret {
+    type Error = String;
+
+    fn try_from(source: SecretSource) -> Result<Self, Self::Error> {
+        let secret = match source {
+            SecretSource::Inline(secret) => secret,
+            SecretSource::Env { env } => std::env::var(&env).map_err(|e| {
+                format!("failed to read secret from environment variable {env}: {e}")
+            })?,
+            SecretSource::File { file } => std::fs::read_to_string(&file)
+                .map_err(|e| format!("failed to read secret from {}: {e}", file.display()))?
+                .trim_end()
+                .to_owned(),
+        };
+
+        if secret.is_empty() {
+            return Err("secret is empty".into());
+        }
+
+        Ok(Self(secret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        secret: Secret,
+    }
+
+    fn secret(config: &str) -> Result<Secret, toml::de::Error> {
+        toml::from_str::<Config>(config).map(|config| config.secret)
+    }
+
+    #[test]
+    fn reads_inline_secret() {
+        assert_eq!(secret(r#"secret = "pk_1""#).unwrap().expose(), "pk_1");
+    }
+
+    #[test]
+    fn reads_secret_from_env() {
+        std::env::set_var("CLICKY_TEST_SECRET", "pk_2");
+
+        assert_eq!(
+            secret(r#"secret = { env = "CLICKY_TEST_SECRET" }"#)
+                .unwrap()
+                .expose(),
+            "pk_2"
+        );
+    }
+
+    #[test]
+    fn reads_secret_from_file_without_trailing_newline() {
+        let path = std::env::temp_dir().join(format!("clicky-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "pk_3\n").unwrap();
+
+        let config = format!("secret = {{ file = {:?} }}", path.display().to_string());
+        assert_eq!(secret(&config).unwrap().expose(), "pk_3");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_env() {
+        let err = secret(r#"secret = { env = "CLICKY_TEST_SECRET_UNSET" }"#).unwrap_err();
+
+        assert!(err.to_string().contains("CLICKY_TEST_SECRET_UNSET"));
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let config = toml::from_str::<Config>(r#"secret = "pk_4""#).unwrap();
+
+        assert!(!format!("{config:?}").contains("pk_4"));
+    }
+}
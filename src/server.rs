@@ -0,0 +1,614 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::clickup::{
+    auth::{OAuthFlow, TokenStore},
+    cache::CacheStats,
+    client::{ClickupClient, ClickupConnection},
+    error::ClickupError,
+    task::TaskId,
+    team::TeamId,
+    time::TimeRollup,
+    webhooks::{
+        events::Event as WebhookEvent,
+        payload::Payload,
+        request::Health,
+        signature::{self, WebhookSecrets},
+    },
+};
+use crate::config::Config;
+use crate::events::{
+    self,
+    store::{EventFilter, EventId, EventStatus, EventStore, StoredEvent},
+    Job, Queue, QueueFull,
+};
+
+/// State shared by all request handlers.
+#[derive(Debug)]
+pub struct AppState {
+    pub config: Config,
+    pub tokens: TokenStore,
+    /// The connection pool used by all ClickUp clients.
+    pub clickup: ClickupConnection,
+    pub secrets: WebhookSecrets,
+    pub events: EventStore,
+    pub oauth: Option<OAuthFlow>,
+}
+
+impl AppState {
+    /// Opens the persisted state in the configured `data_dir`.
+    pub fn open(config: Config) -> color_eyre::Result<Self> {
+        use color_eyre::eyre::WrapErr;
+
+        let tokens = TokenStore::open(config.data_dir.join("oauth_tokens.json"))?;
+        let secrets = WebhookSecrets::open(config.data_dir.join("webhooks.json"))?;
+        let events_path = config.data_dir.join("events.sqlite");
+        let events = EventStore::open(&events_path)
+            .wrap_err_with(|| format!("could not open {}", events_path.display()))?;
+        let clickup = ClickupConnection::new(&config.clickup)
+            .wrap_err("could not build the ClickUp HTTP client")?;
+        let oauth = config.oauth.clone().map(OAuthFlow::new);
+
+        Ok(Self {
+            config,
+            tokens,
+            clickup,
+            secrets,
+            events,
+            oauth,
+        })
+    }
+
+    /// A ClickUp client for the named credentials, once they are installed.
+    pub fn client(&self, credentials: &str) -> Option<ClickupClient> {
+        let token = self.config.token(credentials, &self.tokens)?;
+        Some(self.clickup.client(token))
+    }
+}
+
+/// Registers the configured webhooks and serves the API until the server stops.
+pub async fn serve(state: AppState) -> color_eyre::Result<()> {
+    let state = Arc::new(state);
+
+    tokio::task::spawn(register_webhooks(state.clone(), None));
+
+    let queue = Queue::start(&state.config.queue, {
+        let state = state.clone();
+        move |job: Job| {
+            let state = state.clone();
+            async move { events::handle(&state, &job).await }
+        }
+    });
+
+    tokio::task::spawn({
+        let state = state.clone();
+        let queue = queue.clone();
+        async move {
+            if let Err(err) = events::recover(&state, &queue).await {
+                tracing::error!("Error resuming unfinished events: {:?}", err);
+            }
+        }
+    });
+
+    let app = Router::new()
+        .route("/", get(root))
+        .route("/oauth/start", get(oauth_start))
+        .route("/oauth/callback", get(oauth_callback))
+        .route("/webhook/:webhook_id", post(webhook))
+        .route("/admin/webhooks", get(admin_webhooks))
+        .route("/admin/webhooks/:name/enable", post(admin_enable_webhook))
+        .route("/admin/cache", get(admin_cache))
+        .route("/admin/milestones/time", get(admin_milestone_time))
+        .route("/admin/events", get(admin_events))
+        .route("/admin/events/replay", post(admin_replay_events))
+        .route("/admin/events/:id/replay", post(admin_replay_event))
+        .layer(Extension(queue))
+        .layer(Extension(state.clone()));
+
+    tracing::debug!("listening on {}", state.config.listen);
+    axum::Server::bind(&state.config.listen)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+/// Registers the configured webhooks, or only those of `installed` after an OAuth installation.
+async fn register_webhooks(state: Arc<AppState>, installed: Option<TeamId>) {
+    for webhook in &state.config.webhooks {
+        let credential = state
+            .config
+            .credential(&webhook.credentials)
+            .expect("validated config references known credentials");
+
+        if installed.is_some() && credential.team_id != installed {
+            continue;
+        }
+
+        let Some(client) = credential
+            .token(&state.tokens)
+            .map(|token| state.clickup.client(token))
+        else {
+            tracing::warn!(
+                "Not registering webhook {}, credentials {} are not installed yet",
+                webhook.name,
+                credential.name
+            );
+            continue;
+        };
+
+        match client
+            .ensure_webhook(webhook.team_id, &webhook.endpoint, webhook.events())
+            .await
+        {
+            Ok(registered) => store_secret(&state, &webhook.name, &registered),
+            Err(err) => tracing::error!("Error registering webhook {}: {:?}", webhook.name, err),
+        }
+    }
+}
+
+fn store_secret(
+    state: &AppState,
+    name: &str,
+    webhook: &crate::clickup::webhooks::request::Webhook,
+) {
+    let Some(secret) = &webhook.secret else {
+        tracing::warn!("ClickUp returned no secret for webhook {}", webhook.id);
+        return;
+    };
+
+    if let Err(err) = state.secrets.insert(name, &webhook.id, secret) {
+        tracing::error!("Error storing secret of webhook {}: {:?}", webhook.id, err);
+    }
+}
+
+async fn root() -> &'static str {
+    "Hello, World!"
+}
+
+async fn oauth_start(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+    match &state.oauth {
+        Some(oauth) => Ok(Redirect::to(oauth.start().as_str())),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthCallback {
+    code: String,
+    state: String,
+}
+
+async fn oauth_callback(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(callback): Query<OAuthCallback>,
+) -> Result<String, StatusCode> {
+    let Some(oauth) = &state.oauth else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    if !oauth.finish(&callback.state) {
+        tracing::warn!("OAuth callback with unknown or expired state");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let token = oauth
+        .app
+        .exchange_code(&state.clickup, &callback.code)
+        .await
+        .map_err(|err| {
+            tracing::error!("Error exchanging OAuth code: {:?}", err);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    let client = state.clickup.client(token.clone());
+    let teams = client.get_authorized_teams().await.map_err(|err| {
+        tracing::error!("Error getting authorized teams: {:?}", err);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    for team in &teams {
+        state.tokens.insert(team.id, &token).map_err(|err| {
+            tracing::error!("Error storing OAuth token for team {}: {:?}", team.id, err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        tracing::info!("Installed in team {} ({})", team.name, team.id);
+
+        tokio::task::spawn(register_webhooks(state.clone(), Some(team.id)));
+    }
+
+    let names: Vec<_> = teams.iter().map(|team| team.name.as_str()).collect();
+    Ok(format!("clicky is installed in {}", names.join(", ")))
+}
+
+/// Checks the `Authorization: Bearer` header of a request to the `/admin` endpoints.
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    use subtle::ConstantTimeEq;
+
+    let Some(admin_token) = &state.config.admin_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let given = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if bool::from(given.as_bytes().ct_eq(admin_token.expose().as_bytes())) {
+        Ok(())
+    } else {
+        tracing::warn!("Rejected unauthorized admin request");
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// A configured webhook as ClickUp currently sees it.
+#[derive(Debug, Serialize)]
+struct WebhookHealth {
+    name: String,
+    id: String,
+    endpoint: String,
+    events: Vec<WebhookEvent>,
+    health: Health,
+}
+
+/// Lists the ClickUp webhooks of the configured endpoints with their health.
+async fn admin_webhooks(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<WebhookHealth>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let mut webhooks = Vec::new();
+    for webhook in &state.config.webhooks {
+        let Some(client) = state.client(&webhook.credentials) else {
+            continue;
+        };
+
+        let registered = client.list_webhooks(webhook.team_id).await.map_err(|err| {
+            tracing::error!(
+                "Error listing webhooks of team {}: {:?}",
+                webhook.team_id,
+                err
+            );
+            StatusCode::BAD_GATEWAY
+        })?;
+
+        webhooks.extend(
+            registered
+                .into_iter()
+                .filter(|registered| registered.endpoint == webhook.endpoint)
+                .map(|registered| WebhookHealth {
+                    name: webhook.name.clone(),
+                    id: registered.id,
+                    endpoint: registered.endpoint,
+                    events: registered.events,
+                    health: registered.health,
+                }),
+        );
+    }
+
+    Ok(axum::Json(webhooks))
+}
+
+/// Re-enables a suspended webhook, or re-registers it when it was deleted in ClickUp.
+async fn admin_enable_webhook(
+    Path(name): Path<String>,
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::Json<WebhookHealth>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let webhook = state.config.webhook(&name).ok_or(StatusCode::NOT_FOUND)?;
+    let client = state
+        .client(&webhook.credentials)
+        .ok_or(StatusCode::CONFLICT)?;
+
+    let registered = client
+        .ensure_webhook(webhook.team_id, &webhook.endpoint, webhook.events())
+        .await
+        .map_err(|err| {
+            tracing::error!("Error enabling webhook {}: {:?}", name, err);
+            StatusCode::BAD_GATEWAY
+        })?;
+    store_secret(&state, &name, &registered);
+
+    Ok(axum::Json(WebhookHealth {
+        name,
+        id: registered.id,
+        endpoint: registered.endpoint,
+        events: registered.events,
+        health: registered.health,
+    }))
+}
+
+/// Reports the hits and misses of the task cache.
+async fn admin_cache(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::Json<CacheStats>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    Ok(axum::Json(state.clickup.tasks().stats()))
+}
+
+/// The time estimated for and spent on a milestone, summed up over all tasks under it.
+#[derive(Debug, Serialize)]
+struct MilestoneTime {
+    space: String,
+    task: TaskId,
+    name: Option<String>,
+    #[serde(flatten)]
+    time: TimeRollup,
+}
+
+/// Reports tracked against estimated time of the milestones of all configured spaces.
+async fn admin_milestone_time(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<MilestoneTime>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let mut milestones = Vec::new();
+    for space in &state.config.spaces {
+        let Some(client) = state.client(&space.credentials) else {
+            continue;
+        };
+
+        let bad_gateway = |err: ClickupError| {
+            tracing::error!(
+                "Error summing up time of milestones in {}: {:?}",
+                space.id,
+                err
+            );
+            StatusCode::BAD_GATEWAY
+        };
+
+        let mut tasks = Vec::new();
+        for id in space.milestone_tasks() {
+            tasks.push(client.get_task(id).await.map_err(bad_gateway)?);
+        }
+        let Some(team) = tasks.first().and_then(|task| task.team_id) else {
+            tracing::error!("Milestones of {} have no team", space.id);
+            return Err(StatusCode::BAD_GATEWAY);
+        };
+        let times = client
+            .time_rollups(team, &tasks)
+            .await
+            .map_err(bad_gateway)?;
+
+        for (task, time) in tasks.into_iter().zip(times) {
+            milestones.push(MilestoneTime {
+                space: space.id.to_string(),
+                task: task.id,
+                name: task.name,
+                time,
+            });
+        }
+    }
+
+    Ok(axum::Json(milestones))
+}
+
+fn store_error(err: rusqlite::Error) -> StatusCode {
+    tracing::error!("Error accessing the event store: {:?}", err);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Lists the stored events, filtered by `status` and a `from`/`to` range of RFC 3339 timestamps.
+async fn admin_events(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(filter): Query<EventFilter>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<StoredEvent>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let events = state.events.list(&filter).map_err(store_error)?;
+    Ok(axum::Json(events))
+}
+
+/// Processes a stored event again, whatever its status.
+async fn admin_replay_event(
+    Path(id): Path<i64>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(queue): Extension<Queue>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<EventId>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let id = EventId(id);
+    if events::replay(&state, &queue, id)
+        .await
+        .map_err(store_error)?
+    {
+        Ok(axum::Json(vec![id]))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayRange {
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    status: Option<EventStatus>,
+}
+
+/// Processes the events received in a time range again.
+async fn admin_replay_events(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(queue): Extension<Queue>,
+    Query(range): Query<ReplayRange>,
+    headers: HeaderMap,
+) -> Result<axum::Json<Vec<EventId>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+
+    let events = state
+        .events
+        .list(&EventFilter {
+            status: range.status,
+            from: Some(range.from),
+            to: Some(range.to),
+            limit: None,
+        })
+        .map_err(store_error)?;
+
+    let mut replayed = Vec::new();
+    for event in events {
+        if events::replay(&state, &queue, event.id)
+            .await
+            .map_err(store_error)?
+        {
+            replayed.push(event.id);
+        }
+    }
+
+    tracing::info!("Replaying {} events", replayed.len());
+    Ok(axum::Json(replayed))
+}
+
+/// Checks the `X-Signature` of a delivery to the webhook `name` against that webhook's secret,
+/// and that the delivery is for the ClickUp webhook the secret belongs to.
+fn verify_signature(state: &AppState, name: &str, headers: &HeaderMap, payload: &[u8]) -> bool {
+    #[derive(Deserialize)]
+    struct Envelope {
+        webhook_id: String,
+    }
+
+    let Some(stored) = state.secrets.get(name) else {
+        tracing::warn!(
+            "Rejected payload of webhook {} without a known secret",
+            name
+        );
+        return false;
+    };
+
+    let Some(header) = headers.get(signature::HEADER) else {
+        tracing::warn!(
+            "Rejected payload of webhook {} without {} header",
+            name,
+            signature::HEADER
+        );
+        return false;
+    };
+
+    let valid = header
+        .to_str()
+        .map(|header| signature::verify(&stored.secret, payload, header))
+        .unwrap_or(false);
+    if !valid {
+        tracing::warn!(
+            "Rejected payload of webhook {} with invalid signature",
+            name
+        );
+        return false;
+    }
+
+    match serde_json::from_slice::<Envelope>(payload) {
+        Ok(envelope) if envelope.webhook_id == stored.webhook_id => true,
+        Ok(envelope) => {
+            tracing::warn!(
+                "Rejected payload of ClickUp webhook {} delivered to webhook {} ({})",
+                envelope.webhook_id,
+                name,
+                stored.webhook_id
+            );
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Rejected payload of webhook {} without webhook_id", name);
+            false
+        }
+    }
+}
+
+/// Verifies, stores and enqueues a delivery, acknowledging it before it is processed.
+async fn webhook(
+    Path(webhook_id): Path<String>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(queue): Extension<Queue>,
+    headers: HeaderMap,
+    payload: bytes::Bytes,
+) -> impl IntoResponse {
+    if state.config.webhook(&webhook_id).is_none() {
+        tracing::error!("Payload received for unknown webhook {}", webhook_id);
+        return StatusCode::NOT_FOUND;
+    }
+
+    if !verify_signature(&state, &webhook_id, &headers, &payload) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let Ok(raw) = std::str::from_utf8(&payload) else {
+        tracing::error!("Invalid payload received: not UTF-8");
+        return StatusCode::BAD_REQUEST;
+    };
+
+    let id = match state.events.insert(&webhook_id, raw) {
+        Ok(id) => id,
+        Err(err) => return store_error(err),
+    };
+
+    let event = match serde_json::from_str::<Payload>(raw) {
+        Ok(event) => event,
+        Err(err) => {
+            tracing::error!("Invalid payload received: {}", err);
+            if let Err(err) =
+                state
+                    .events
+                    .finish_attempt(id, EventStatus::Dead, Some(&err.to_string()))
+            {
+                store_error(err);
+            }
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    // Whatever the event changed, the cached task no longer reflects it.
+    if let Some(task_id) = event.task_id() {
+        state.clickup.tasks().invalidate(task_id);
+    }
+
+    let items = event.history_items().iter().map(|item| item.id.as_str());
+    match state.events.record_history_items(id, items) {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::debug!("Ignoring redelivered {} event {}", event.event(), id);
+            if let Err(err) = state
+                .events
+                .finish_attempt(id, EventStatus::Duplicate, None)
+            {
+                return store_error(err);
+            }
+            return StatusCode::OK;
+        }
+        Err(err) => return store_error(err),
+    }
+
+    let job = Job {
+        id,
+        webhook: webhook_id,
+        payload: event,
+    };
+
+    match queue.push(job) {
+        Ok(()) => StatusCode::OK,
+        Err(QueueFull) => {
+            tracing::warn!("Event queue is full, asking ClickUp to retry later");
+            // ClickUp delivers it again, so it should not be replayed from the store as well.
+            if let Err(err) = state.events.discard(id) {
+                store_error(err);
+            }
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
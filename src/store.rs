@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A small key-value map that is written through to a JSON file on every change.
+///
+/// Meant for state that changes rarely and must survive restarts, such as OAuth tokens and
+/// webhook secrets. The file is only readable by the current user as it usually holds secrets.
+#[derive(Debug)]
+pub struct JsonStore<K, V> {
+    path: PathBuf,
+    entries: RwLock<HashMap<K, V>>,
+}
+
+impl<K, V> JsonStore<K, V>
+where
+    K: Eq + Hash + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Opens the store at `path`, starting out empty when the file does not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let entries = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            path,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.entries
+            .read()
+            .expect("store lock poisoned")
+            .get(key)
+            .cloned()
+    }
+
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.entries
+            .read()
+            .expect("store lock poisoned")
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn insert(&self, key: K, value: V) -> io::Result<()> {
+        let mut entries = self.entries.write().expect("store lock poisoned");
+        entries.insert(key, value);
+        self.persist(&entries)
+    }
+
+    pub fn remove(&self, key: &K) -> io::Result<Option<V>> {
+        let mut entries = self.entries.write().expect("store lock poisoned");
+        let removed = entries.remove(key);
+        if removed.is_some() {
+            self.persist(&entries)?;
+        }
+        Ok(removed)
+    }
+
+    fn persist(&self, entries: &HashMap<K, V>) -> io::Result<()> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a truncated store behind.
+        let tmp = self.path.with_extension("tmp");
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(entries)?)?;
+        file.sync_all()?;
+
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("clicky-store-{}", uuid::Uuid::new_v4()))
+            .join("store.json")
+    }
+
+    #[test]
+    fn entries_survive_reopening() {
+        let path = path();
+
+        let store = JsonStore::<u64, String>::open(&path).unwrap();
+        store.insert(1, "one".into()).unwrap();
+        store.insert(2, "two".into()).unwrap();
+        store.remove(&2).unwrap();
+
+        let reopened = JsonStore::<u64, String>::open(&path).unwrap();
+        assert_eq!(reopened.get(&1), Some("one".into()));
+        assert_eq!(reopened.get(&2), None);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}